@@ -1,4 +1,15 @@
-use clap::Parser;
+use std::{env, path::PathBuf};
+
+use anyhow::Result;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    input::{self, Scripted, StdinLines},
+    output::Verbosity,
+};
+
+/// Environment variable holding keys in the `--keys` format.
+pub const KEYS_ENV: &str = "GITFLOW_KEYS";
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -6,12 +17,96 @@ pub struct Args {
     /// when true, will auto use upstream or use the branch with same name of remote branch to judge if we can push something
     #[arg(short, long, default_value_t = false)]
     pub auto_upstream: bool,
+
+    /// show the commands that would change the repository instead of running them
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// answer prompts with these comma separated keys and lines instead of the terminal, e.g. "a,y,c,i"
+    #[arg(long, group = "input")]
+    pub keys: Option<String>,
+
+    /// answer prompts with the lines of this file, skipping blank lines and lines starting with '#'
+    #[arg(long, value_name = "FILE", group = "input")]
+    pub replay: Option<PathBuf>,
+
+    /// answer prompts with the lines read from stdin
+    #[arg(long, default_value_t = false, group = "input")]
+    pub stdin: bool,
+
+    /// hide the git commands that run
+    #[arg(
+        short,
+        long,
+        default_value_t = false,
+        global = true,
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+
+    /// also show the git commands that only read the repository, -vv adds their timing and the environment
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// how to print what happens: styled text, or one JSON event per line
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub output: OutputFormat,
+
+    /// the colors to print with: dark, light, high-contrast, monochrome or a theme of the
+    /// configuration file
+    #[arg(long, global = true)]
+    pub theme: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the recommended next actions without asking anything
+    Next {
+        /// print them as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Print a summary of the branch, its upstream, the changed files and the stashes
+    Status,
 }
 
 impl Args {
     pub fn new() -> Self {
         Args::parse()
     }
+
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
+
+    /// Makes the prompts read from the source chosen on the command line, then from
+    /// `GITFLOW_KEYS`, and from the terminal otherwise.
+    pub fn install_input_source(&self) -> Result<()> {
+        if let Some(keys) = &self.keys {
+            input::set_source(Scripted::from_keys(keys));
+        } else if let Some(path) = &self.replay {
+            input::set_source(Scripted::from_file(path)?);
+        } else if self.stdin {
+            input::set_source(StdinLines);
+        } else if let Ok(keys) = env::var(KEYS_ENV) {
+            input::set_source(Scripted::from_keys(&keys));
+        }
+        Ok(())
+    }
 }
 
 impl Default for Args {
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{
+    git::Commit,
+    status::{BranchInfo, RepoSnapshot},
+};
+
+mod cli;
+mod dry_run;
+mod memory;
+#[cfg(feature = "native")]
+mod native;
+
+pub use cli::CliBackend;
+pub use dry_run::DryRunBackend;
+pub use memory::{MemoryBackend, MemoryRepo};
+#[cfg(feature = "native")]
+pub use native::NativeBackend;
+
+/// The git operations the flow relies on.
+pub trait GitBackend {
+    fn init(&self) -> Result<()>;
+    /// Reads the repository, `None` when not inside a git work tree.
+    fn status(&self) -> Result<Option<RepoSnapshot>>;
+    /// The top directory of the work tree, as `git rev-parse --show-toplevel` prints it.
+    fn work_tree(&self) -> Result<PathBuf>;
+    /// Counts the commits HEAD is ahead of and behind `base`.
+    fn ahead_behind(&self, base: &str) -> Result<(u32, u32)>;
+    /// Lists the commits of `upstream` that HEAD doesn't contain yet.
+    fn incoming_commits(&self, upstream: &str) -> Result<String>;
+    /// Summarizes how the work tree differs from `remote/branch`, as `git diff --stat` does.
+    fn diff_stat(&self, remote: &str, branch: &str) -> Result<String>;
+    fn stash_count(&self) -> Result<usize>;
+    /// Lists local branches, or the branches of `remote` without the remote prefix.
+    fn branches(&self, remote: Option<&str>) -> Result<Vec<String>>;
+    /// Like [`GitBackend::branches`] with the last commit of each branch, most recent first.
+    fn branch_details(&self, remote: Option<&str>) -> Result<Vec<BranchInfo>>;
+    fn remotes(&self) -> Result<Vec<String>>;
+    fn add_remote(&self, name: &str, url: &str) -> Result<()>;
+    fn get_config(&self, key: &str) -> Result<Option<String>>;
+    fn set_config(&self, key: &str, value: &str) -> Result<()>;
+    fn set_upstream(&self, remote: &str, branch: &str) -> Result<()>;
+    fn add_all(&self) -> Result<()>;
+    /// Stages the changes of `paths`, deletions and untracked files included.
+    fn stage(&self, paths: &[String]) -> Result<()>;
+    /// Takes the staged changes of `paths` back out of the index, leaving the files as they are.
+    fn unstage(&self, paths: &[String]) -> Result<()>;
+    /// The unstaged changes of `path` as a unified diff, empty when there are none.
+    fn diff_file(&self, path: &str) -> Result<String>;
+    /// The staged changes of `path` as a unified diff, empty when there are none.
+    fn diff_staged(&self, path: &str) -> Result<String>;
+    /// The content of the untracked file `path` as a diff adding it.
+    fn diff_untracked(&self, path: &str) -> Result<String>;
+    /// Saves the index with the work tree content of `paths` to [`crate::git::BACKUP_REF`],
+    /// leaving both as they are.
+    fn backup(&self, paths: &[String], message: &str) -> Result<()>;
+    /// Throws away the unstaged changes of the tracked `paths`, keeping what's staged, or all
+    /// their changes since HEAD when `staged`.
+    fn restore(&self, paths: &[String], staged: bool) -> Result<()>;
+    /// Deletes the untracked `paths`.
+    fn clean(&self, paths: &[String]) -> Result<()>;
+    /// Stages `patch`, a diff of the work tree against the index, as `git apply --cached`.
+    fn apply_cached(&self, patch: &str) -> Result<()>;
+    fn commit(&self, commit: &Commit) -> Result<()>;
+    fn merge(&self, branch: &str) -> Result<()>;
+    fn push(&self, remote: &str, branch: &str) -> Result<()>;
+    fn push_set_upstream(&self, remote: &str, branch: &str) -> Result<()>;
+    fn push_force_with_lease(&self, remote: &str, branch: &str) -> Result<()>;
+    fn pull(&self, remote: &str, branch: &str) -> Result<()>;
+    fn pull_rebase(&self, remote: &str, branch: &str) -> Result<()>;
+    fn fetch(&self, remote: &str) -> Result<()>;
+    fn checkout(&self, branch: &str) -> Result<()>;
+    fn create_checkout(&self, branch: &str) -> Result<()>;
+    /// Drives a paused operation, e.g. `operation("rebase", "continue")`.
+    fn operation(&self, command: &str, action: &str) -> Result<()>;
+    fn bisect(&self, subcommand: &str) -> Result<()>;
+}
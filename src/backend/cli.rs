@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use super::GitBackend;
+use crate::{
+    git::{self, Commit},
+    status::{BranchInfo, GitRemoteBranch, RepoSnapshot},
+};
+
+/// Runs every operation through the `git` binary.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CliBackend;
+
+impl GitBackend for CliBackend {
+    fn init(&self) -> Result<()> {
+        git::init()
+    }
+
+    fn status(&self) -> Result<Option<RepoSnapshot>> {
+        RepoSnapshot::capture()
+    }
+
+    fn work_tree(&self) -> Result<PathBuf> {
+        git::work_tree()
+    }
+
+    fn ahead_behind(&self, base: &str) -> Result<(u32, u32)> {
+        git::ahead_behind(base)
+    }
+
+    fn incoming_commits(&self, upstream: &str) -> Result<String> {
+        git::incoming_commits(upstream)
+    }
+
+    fn diff_stat(&self, remote: &str, branch: &str) -> Result<String> {
+        git::diff_remote_stat(remote.to_string(), branch.to_string())
+    }
+
+    fn stash_count(&self) -> Result<usize> {
+        git::stash_count()
+    }
+
+    fn branches(&self, remote: Option<&str>) -> Result<Vec<String>> {
+        git::get_branches(remote.map(String::from))
+    }
+
+    fn branch_details(&self, remote: Option<&str>) -> Result<Vec<BranchInfo>> {
+        git::branch_details(remote)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        git::get_remote_names()
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        git::add_remote(name, url)
+    }
+
+    fn get_config(&self, key: &str) -> Result<Option<String>> {
+        git::get_config(key)
+    }
+
+    fn set_config(&self, key: &str, value: &str) -> Result<()> {
+        git::set_config(key, value)
+    }
+
+    fn set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        git::set_upstream(remote, branch)
+    }
+
+    fn add_all(&self) -> Result<()> {
+        git::add_all()
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        git::stage(paths)
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        git::unstage(paths)
+    }
+
+    fn diff_file(&self, path: &str) -> Result<String> {
+        git::diff_file(path)
+    }
+
+    fn diff_staged(&self, path: &str) -> Result<String> {
+        git::diff_staged(path)
+    }
+
+    fn diff_untracked(&self, path: &str) -> Result<String> {
+        git::diff_untracked(path)
+    }
+
+    fn backup(&self, paths: &[String], message: &str) -> Result<()> {
+        git::backup(paths, message)
+    }
+
+    fn restore(&self, paths: &[String], staged: bool) -> Result<()> {
+        git::restore(paths, staged)
+    }
+
+    fn clean(&self, paths: &[String]) -> Result<()> {
+        git::clean(paths)
+    }
+
+    fn apply_cached(&self, patch: &str) -> Result<()> {
+        git::apply_cached(patch)
+    }
+
+    fn commit(&self, commit: &Commit) -> Result<()> {
+        git::commit(commit)
+    }
+
+    fn merge(&self, branch: &str) -> Result<()> {
+        git::merge(branch)
+    }
+
+    fn push(&self, remote: &str, branch: &str) -> Result<()> {
+        git::push(Some(GitRemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        }))
+    }
+
+    fn push_set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        git::push_set_upstream(remote, branch)
+    }
+
+    fn push_force_with_lease(&self, remote: &str, branch: &str) -> Result<()> {
+        git::push_force_with_lease(remote, branch)
+    }
+
+    fn pull(&self, remote: &str, branch: &str) -> Result<()> {
+        git::pull(Some((remote, branch)))
+    }
+
+    fn pull_rebase(&self, remote: &str, branch: &str) -> Result<()> {
+        git::pull_rebase(remote, branch)
+    }
+
+    fn fetch(&self, remote: &str) -> Result<()> {
+        git::fetch(remote)
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        git::checkout(branch)
+    }
+
+    fn create_checkout(&self, branch: &str) -> Result<()> {
+        git::create_checkout(branch)
+    }
+
+    fn operation(&self, command: &str, action: &str) -> Result<()> {
+        git::operation(command, action)
+    }
+
+    fn bisect(&self, subcommand: &str) -> Result<()> {
+        git::bisect(subcommand)
+    }
+}
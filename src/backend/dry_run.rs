@@ -0,0 +1,241 @@
+use std::{cell::OnceCell, path::PathBuf};
+
+use anyhow::Result;
+
+use super::{GitBackend, MemoryBackend, MemoryRepo};
+use crate::{
+    git::Commit,
+    output::dry_run_output,
+    status::{BranchInfo, GitRemoteBranch, RepoSnapshot},
+};
+
+/// Shows the commands that would change the repository instead of running them. Queries
+/// run against the wrapped backend until the first change; from then on a copy of the
+/// repository kept in memory simulates the state the changes would lead to.
+pub struct DryRunBackend<B> {
+    inner: B,
+    simulated: OnceCell<MemoryBackend>,
+}
+
+impl<B: GitBackend> DryRunBackend<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            simulated: OnceCell::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Where queries go: the repository, or its simulation once something changed.
+    fn current(&self) -> &dyn GitBackend {
+        match self.simulated.get() {
+            Some(simulated) => simulated,
+            None => &self.inner,
+        }
+    }
+
+    fn simulated(&self) -> Result<&MemoryBackend> {
+        if self.simulated.get().is_none() {
+            let repo = self.copy_repo()?;
+            let _ = self.simulated.set(MemoryBackend::new(repo));
+        }
+        Ok(self.simulated.get().expect("the simulation was just set"))
+    }
+
+    /// Reads what the simulation starts from out of the wrapped backend.
+    fn copy_repo(&self) -> Result<MemoryRepo> {
+        let Some(snapshot) = self.inner.status()? else {
+            return Ok(MemoryRepo::default());
+        };
+        let remotes = self.inner.remotes()?;
+        let mut remote_branches = vec![];
+        for remote in &remotes {
+            for branch in self.inner.branches(Some(remote))? {
+                remote_branches.push(GitRemoteBranch {
+                    remote: remote.clone(),
+                    branch,
+                });
+            }
+        }
+        Ok(MemoryRepo {
+            initialized: true,
+            commits: u32::from(snapshot.oid.is_some()),
+            branches: self.inner.branches(None)?,
+            upstream: snapshot.upstream_branch(),
+            stashes: self.inner.stash_count()?,
+            branch: snapshot.branch,
+            remotes,
+            remote_branches,
+            ahead: snapshot.ahead,
+            behind: snapshot.behind,
+            entries: snapshot.entries,
+            operation: snapshot.operation,
+            ..Default::default()
+        })
+    }
+
+    /// Applies `change` to the simulation and shows the commands it stands for.
+    fn simulate(&self, change: impl FnOnce(&MemoryBackend) -> Result<()>) -> Result<()> {
+        let simulated = self.simulated()?;
+        let done = simulated.commands().len();
+        let result = change(simulated);
+        for command in &simulated.commands()[done..] {
+            dry_run_output(command)?;
+        }
+        result
+    }
+}
+
+impl<B: GitBackend> GitBackend for DryRunBackend<B> {
+    fn init(&self) -> Result<()> {
+        self.simulate(|git| git.init())
+    }
+
+    fn status(&self) -> Result<Option<RepoSnapshot>> {
+        self.current().status()
+    }
+
+    fn work_tree(&self) -> Result<PathBuf> {
+        self.current().work_tree()
+    }
+
+    fn ahead_behind(&self, base: &str) -> Result<(u32, u32)> {
+        self.current().ahead_behind(base)
+    }
+
+    fn incoming_commits(&self, upstream: &str) -> Result<String> {
+        self.current().incoming_commits(upstream)
+    }
+
+    fn diff_stat(&self, remote: &str, branch: &str) -> Result<String> {
+        self.current().diff_stat(remote, branch)
+    }
+
+    fn stash_count(&self) -> Result<usize> {
+        self.current().stash_count()
+    }
+
+    fn branches(&self, remote: Option<&str>) -> Result<Vec<String>> {
+        self.current().branches(remote)
+    }
+
+    fn branch_details(&self, remote: Option<&str>) -> Result<Vec<BranchInfo>> {
+        self.current().branch_details(remote)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.current().remotes()
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        self.simulate(|git| git.add_remote(name, url))
+    }
+
+    fn get_config(&self, key: &str) -> Result<Option<String>> {
+        if let Some(value) = self.simulated.get().map(|git| git.get_config(key)) {
+            if let Some(value) = value? {
+                return Ok(Some(value));
+            }
+        }
+        self.inner.get_config(key)
+    }
+
+    fn set_config(&self, key: &str, value: &str) -> Result<()> {
+        self.simulate(|git| git.set_config(key, value))
+    }
+
+    fn set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.set_upstream(remote, branch))
+    }
+
+    fn add_all(&self) -> Result<()> {
+        self.simulate(|git| git.add_all())
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        self.simulate(|git| git.stage(paths))
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        self.simulate(|git| git.unstage(paths))
+    }
+
+    fn diff_file(&self, path: &str) -> Result<String> {
+        self.current().diff_file(path)
+    }
+
+    fn diff_staged(&self, path: &str) -> Result<String> {
+        self.current().diff_staged(path)
+    }
+
+    fn diff_untracked(&self, path: &str) -> Result<String> {
+        self.current().diff_untracked(path)
+    }
+
+    fn backup(&self, paths: &[String], message: &str) -> Result<()> {
+        self.simulate(|git| git.backup(paths, message))
+    }
+
+    fn restore(&self, paths: &[String], staged: bool) -> Result<()> {
+        self.simulate(|git| git.restore(paths, staged))
+    }
+
+    fn clean(&self, paths: &[String]) -> Result<()> {
+        self.simulate(|git| git.clean(paths))
+    }
+
+    fn apply_cached(&self, patch: &str) -> Result<()> {
+        self.simulate(|git| git.apply_cached(patch))
+    }
+
+    fn commit(&self, commit: &Commit) -> Result<()> {
+        self.simulate(|git| git.commit(commit))
+    }
+
+    fn merge(&self, branch: &str) -> Result<()> {
+        self.simulate(|git| git.merge(branch))
+    }
+
+    fn push(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.push(remote, branch))
+    }
+
+    fn push_set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.push_set_upstream(remote, branch))
+    }
+
+    fn push_force_with_lease(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.push_force_with_lease(remote, branch))
+    }
+
+    fn pull(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.pull(remote, branch))
+    }
+
+    fn pull_rebase(&self, remote: &str, branch: &str) -> Result<()> {
+        self.simulate(|git| git.pull_rebase(remote, branch))
+    }
+
+    fn fetch(&self, remote: &str) -> Result<()> {
+        self.simulate(|git| git.fetch(remote))
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        self.simulate(|git| git.checkout(branch))
+    }
+
+    fn create_checkout(&self, branch: &str) -> Result<()> {
+        self.simulate(|git| git.create_checkout(branch))
+    }
+
+    fn operation(&self, command: &str, action: &str) -> Result<()> {
+        self.simulate(|git| git.operation(command, action))
+    }
+
+    fn bisect(&self, subcommand: &str) -> Result<()> {
+        self.simulate(|git| git.bisect(subcommand))
+    }
+}
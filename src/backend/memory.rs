@@ -0,0 +1,587 @@
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+
+use super::GitBackend;
+use crate::{
+    git::{Commit, GitError, BACKUP_REF},
+    status::{BranchInfo, FileState, GitOperation, GitRemoteBranch, RepoSnapshot, StatusEntry},
+};
+
+/// The state of a [`MemoryBackend`], arranged freely before running a flow.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryRepo {
+    pub initialized: bool,
+    /// The number of commits reachable from HEAD.
+    pub commits: u32,
+    /// The current branch, `None` when HEAD is detached.
+    pub branch: Option<String>,
+    pub branches: Vec<String>,
+    pub remotes: Vec<String>,
+    pub remote_branches: Vec<GitRemoteBranch>,
+    pub upstream: Option<GitRemoteBranch>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub entries: Vec<StatusEntry>,
+    pub operation: Option<GitOperation>,
+    pub stashes: usize,
+    /// The number of backups taken before discarding changes.
+    pub backups: usize,
+    pub config: HashMap<String, String>,
+    /// The changes of each path, as `git diff` prints them.
+    pub diffs: HashMap<String, String>,
+    /// Where the files of the repository are, relative to the current directory.
+    pub work_tree: PathBuf,
+}
+
+impl MemoryRepo {
+    /// An initialized repository with one commit on `branch`.
+    pub fn new(branch: &str) -> Self {
+        Self {
+            initialized: true,
+            commits: 1,
+            branch: Some(branch.to_string()),
+            branches: vec![branch.to_string()],
+            ..Default::default()
+        }
+    }
+
+    pub fn with_file(mut self, path: &str, index: FileState, worktree: FileState) -> Self {
+        self.entries.push(StatusEntry {
+            path: path.to_string(),
+            orig_path: None,
+            index,
+            worktree,
+            conflicted: false,
+            submodule: None,
+        });
+        self
+    }
+
+    pub fn with_conflict(mut self, path: &str) -> Self {
+        self.entries.push(StatusEntry {
+            path: path.to_string(),
+            orig_path: None,
+            index: FileState::Unmerged,
+            worktree: FileState::Unmerged,
+            conflicted: true,
+            submodule: None,
+        });
+        self
+    }
+
+    /// Adds `remote` with `branch` on it and tracks it as upstream.
+    pub fn with_upstream(mut self, remote: &str, branch: &str, ahead: u32, behind: u32) -> Self {
+        let remote_branch = GitRemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        };
+        if !self.remotes.iter().any(|name| name == remote) {
+            self.remotes.push(remote.to_string());
+        }
+        self.remote_branches.push(remote_branch.clone());
+        self.upstream = Some(remote_branch);
+        self.ahead = ahead;
+        self.behind = behind;
+        self
+    }
+
+    fn is_upstream(&self, remote: &str, branch: &str) -> bool {
+        self.upstream
+            .as_ref()
+            .is_some_and(|upstream| upstream.remote == remote && upstream.branch == branch)
+    }
+
+    fn has_remote_branch(&self, remote: &str, branch: &str) -> bool {
+        self.remote_branches
+            .iter()
+            .any(|rb| rb.remote == remote && rb.branch == branch)
+    }
+
+    fn integrate_upstream(&mut self) {
+        if self.behind > 0 && self.ahead > 0 {
+            // a merge commit joins both sides
+            self.ahead += 1;
+            self.commits += 1;
+        }
+        self.commits += self.behind;
+        self.behind = 0;
+    }
+}
+
+/// Moves the changes of `entry` into the index, as `git add` does.
+fn stage_entry(entry: &mut StatusEntry) {
+    if entry.conflicted {
+        entry.conflicted = false;
+        entry.index = FileState::Modified;
+    } else {
+        match entry.worktree {
+            FileState::Unmodified | FileState::Ignored => return,
+            FileState::Untracked => entry.index = FileState::Added,
+            FileState::Deleted => entry.index = FileState::Deleted,
+            state if entry.index == FileState::Unmodified => entry.index = state,
+            _ => {}
+        }
+    }
+    entry.worktree = FileState::Unmodified;
+}
+
+/// Keeps the repository in memory so flow transitions can be tested without git.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    repo: RefCell<MemoryRepo>,
+    commands: RefCell<Vec<String>>,
+}
+
+impl MemoryBackend {
+    pub fn new(repo: MemoryRepo) -> Self {
+        Self {
+            repo: RefCell::new(repo),
+            commands: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn repo(&self) -> Ref<'_, MemoryRepo> {
+        self.repo.borrow()
+    }
+
+    pub fn repo_mut(&self) -> RefMut<'_, MemoryRepo> {
+        self.repo.borrow_mut()
+    }
+
+    /// The mutating commands run so far, written as their git equivalent.
+    pub fn commands(&self) -> Vec<String> {
+        self.commands.borrow().clone()
+    }
+
+    fn record(&self, command: String) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    fn push_branch(&self, remote: &str, branch: &str) -> Result<()> {
+        let mut repo = self.repo_mut();
+        if !repo.remotes.iter().any(|name| name == remote) {
+            bail!(GitError::new("Failed to push"));
+        }
+        if repo.is_upstream(remote, branch) {
+            if repo.behind > 0 {
+                bail!(GitError::new("Failed to push"));
+            }
+            repo.ahead = 0;
+        } else if !repo.has_remote_branch(remote, branch) {
+            repo.remote_branches.push(GitRemoteBranch {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl GitBackend for MemoryBackend {
+    fn init(&self) -> Result<()> {
+        self.record("git init".to_string());
+        let mut repo = self.repo_mut();
+        repo.initialized = true;
+        repo.branch = Some("main".to_string());
+        Ok(())
+    }
+
+    fn status(&self) -> Result<Option<RepoSnapshot>> {
+        let repo = self.repo();
+        if !repo.initialized {
+            return Ok(None);
+        }
+        Ok(Some(RepoSnapshot {
+            oid: (repo.commits > 0).then(|| format!("{:040x}", repo.commits)),
+            branch: repo.branch.clone(),
+            upstream: repo
+                .upstream
+                .as_ref()
+                .map(|upstream| format!("{}/{}", upstream.remote, upstream.branch)),
+            ahead: repo.ahead,
+            behind: repo.behind,
+            entries: repo.entries.clone(),
+            operation: repo.operation,
+        }))
+    }
+
+    fn work_tree(&self) -> Result<PathBuf> {
+        Ok(self.repo().work_tree.clone())
+    }
+
+    fn ahead_behind(&self, _base: &str) -> Result<(u32, u32)> {
+        let repo = self.repo();
+        Ok((repo.ahead, repo.behind))
+    }
+
+    fn incoming_commits(&self, _upstream: &str) -> Result<String> {
+        let repo = self.repo();
+        Ok((0..repo.behind)
+            .map(|n| format!("commit {}\n", repo.commits + n + 1))
+            .collect())
+    }
+
+    fn diff_stat(&self, _remote: &str, _branch: &str) -> Result<String> {
+        let repo = self.repo();
+        Ok(repo
+            .entries
+            .iter()
+            .map(|entry| format!(" {} | changed\n", entry.path))
+            .collect())
+    }
+
+    fn stash_count(&self) -> Result<usize> {
+        Ok(self.repo().stashes)
+    }
+
+    fn diff_file(&self, path: &str) -> Result<String> {
+        Ok(self.repo().diffs.get(path).cloned().unwrap_or_default())
+    }
+
+    fn diff_staged(&self, path: &str) -> Result<String> {
+        self.diff_file(path)
+    }
+
+    fn diff_untracked(&self, path: &str) -> Result<String> {
+        self.diff_file(path)
+    }
+
+    fn branches(&self, remote: Option<&str>) -> Result<Vec<String>> {
+        let repo = self.repo();
+        Ok(match remote {
+            Some(remote) => repo
+                .remote_branches
+                .iter()
+                .filter(|rb| rb.remote == remote)
+                .map(|rb| rb.branch.clone())
+                .collect(),
+            None => repo.branches.clone(),
+        })
+    }
+
+    fn branch_details(&self, remote: Option<&str>) -> Result<Vec<BranchInfo>> {
+        let branches = self.branches(remote)?;
+        Ok(branches
+            .into_iter()
+            .map(|name| BranchInfo {
+                name,
+                commit: String::new(),
+                subject: String::new(),
+                committed_at: 0,
+            })
+            .collect())
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        Ok(self.repo().remotes.clone())
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        self.record(format!("git remote add {} {}", name, url));
+        let mut repo = self.repo_mut();
+        if repo.remotes.iter().any(|remote| remote == name) {
+            bail!(GitError::new(format!(
+                "Failed to add remote: remote {} already exists",
+                name
+            )));
+        }
+        repo.remotes.push(name.to_string());
+        Ok(())
+    }
+
+    fn get_config(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.repo().config.get(key).cloned())
+    }
+
+    fn set_config(&self, key: &str, value: &str) -> Result<()> {
+        self.record(format!("git config {} \"{}\"", key, value));
+        self.repo_mut()
+            .config
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!(
+            "git branch --set-upstream-to {}/{}",
+            remote, branch
+        ));
+        let mut repo = self.repo_mut();
+        if !repo.has_remote_branch(remote, branch) {
+            bail!(GitError::new("Failed to set upstream"));
+        }
+        repo.upstream = Some(GitRemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        });
+        Ok(())
+    }
+
+    fn add_all(&self) -> Result<()> {
+        self.record("git add --all".to_string());
+        self.repo_mut().entries.iter_mut().for_each(stage_entry);
+        Ok(())
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        self.record(format!("git add -- {}", paths.join(" ")));
+        self.repo_mut()
+            .entries
+            .iter_mut()
+            .filter(|entry| paths.contains(&entry.path))
+            .for_each(stage_entry);
+        Ok(())
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        self.record(format!("git reset -q -- {}", paths.join(" ")));
+        for entry in self
+            .repo_mut()
+            .entries
+            .iter_mut()
+            .filter(|entry| paths.contains(&entry.path) && entry.is_staged())
+        {
+            entry.worktree = match entry.index {
+                FileState::Added | FileState::Renamed | FileState::Copied => {
+                    entry.orig_path = None;
+                    FileState::Untracked
+                }
+                _ if entry.worktree != FileState::Unmodified => entry.worktree,
+                state => state,
+            };
+            entry.index = FileState::Unmodified;
+        }
+        Ok(())
+    }
+
+    fn backup(&self, _paths: &[String], message: &str) -> Result<()> {
+        self.record(format!(
+            "git update-ref --create-reflog -m {} {}",
+            message, BACKUP_REF
+        ));
+        self.repo_mut().backups += 1;
+        Ok(())
+    }
+
+    fn restore(&self, paths: &[String], staged: bool) -> Result<()> {
+        if staged {
+            self.record(format!(
+                "git restore --worktree --staged --source=HEAD -- {}",
+                paths.join(" ")
+            ));
+        } else {
+            self.record(format!("git restore --worktree -- {}", paths.join(" ")));
+        }
+        let mut repo = self.repo_mut();
+        for entry in repo
+            .entries
+            .iter_mut()
+            .filter(|entry| paths.contains(&entry.path) && entry.worktree != FileState::Untracked)
+        {
+            if staged {
+                entry.index = FileState::Unmodified;
+            }
+            entry.worktree = FileState::Unmodified;
+        }
+        repo.entries
+            .retain(|entry| entry.index != FileState::Unmodified || entry.worktree.is_change());
+        repo.diffs.retain(|path, _| !paths.contains(path));
+        Ok(())
+    }
+
+    fn clean(&self, paths: &[String]) -> Result<()> {
+        self.record(format!("git clean -f -q -- {}", paths.join(" ")));
+        let mut repo = self.repo_mut();
+        repo.entries.retain(|entry| {
+            !(paths.contains(&entry.path) && entry.worktree == FileState::Untracked)
+        });
+        repo.diffs.retain(|path, _| !paths.contains(path));
+        Ok(())
+    }
+
+    fn apply_cached(&self, patch: &str) -> Result<()> {
+        self.record("git apply --cached".to_string());
+        // the file now has staged changes, and maybe unstaged ones left
+        let paths: Vec<_> = patch
+            .lines()
+            .filter_map(|line| line.strip_prefix("+++ b/"))
+            .collect();
+        for entry in self
+            .repo_mut()
+            .entries
+            .iter_mut()
+            .filter(|entry| paths.contains(&entry.path.as_str()))
+        {
+            if entry.index == FileState::Unmodified {
+                entry.index = entry.worktree;
+            }
+        }
+        Ok(())
+    }
+
+    fn commit(&self, commit: &Commit) -> Result<()> {
+        self.record(commit.to_string());
+        let mut repo = self.repo_mut();
+        if commit.amend && repo.commits == 0 {
+            bail!(GitError::new(
+                "Failed to commit: You have nothing to amend."
+            ));
+        }
+        if !commit.amend && !repo.entries.iter().any(StatusEntry::is_staged) {
+            bail!(GitError::new("Failed to commit: nothing to commit."));
+        }
+        for entry in repo.entries.iter_mut() {
+            if entry.is_staged() {
+                entry.index = FileState::Unmodified;
+            }
+        }
+        repo.entries
+            .retain(|entry| entry.index != FileState::Unmodified || entry.worktree.is_change());
+        if !commit.amend {
+            repo.commits += 1;
+            if repo.upstream.is_some() {
+                repo.ahead += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&self, branch: &str) -> Result<()> {
+        self.record(format!("git merge --no-edit {}", branch));
+        let mut repo = self.repo_mut();
+        let (remote, remote_branch) = branch.split_once('/').unwrap_or(("", branch));
+        if repo.is_upstream(remote, remote_branch) {
+            repo.integrate_upstream();
+        } else if repo.branches.iter().any(|name| name == branch)
+            || repo.has_remote_branch(remote, remote_branch)
+        {
+            repo.commits += 1;
+            if repo.upstream.is_some() {
+                repo.ahead += 1;
+            }
+        } else {
+            bail!(GitError::new("Failed to merge branch"));
+        }
+        Ok(())
+    }
+
+    fn push(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!("git push {} {}", remote, branch));
+        self.push_branch(remote, branch)
+    }
+
+    fn push_set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!("git push --set-upstream {} {}", remote, branch));
+        self.push_branch(remote, branch)?;
+        let mut repo = self.repo_mut();
+        repo.upstream = Some(GitRemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        });
+        repo.ahead = 0;
+        repo.behind = 0;
+        Ok(())
+    }
+
+    fn push_force_with_lease(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!("git push --force-with-lease {} {}", remote, branch));
+        let mut repo = self.repo_mut();
+        if !repo.is_upstream(remote, branch) {
+            bail!(GitError::new("Failed to push"));
+        }
+        repo.ahead = 0;
+        repo.behind = 0;
+        Ok(())
+    }
+
+    fn pull(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!("git pull {} {}", remote, branch));
+        let mut repo = self.repo_mut();
+        if !repo.has_remote_branch(remote, branch) {
+            bail!(GitError::new("Failed to pull"));
+        }
+        if repo.is_upstream(remote, branch) {
+            repo.integrate_upstream();
+        }
+        Ok(())
+    }
+
+    fn pull_rebase(&self, remote: &str, branch: &str) -> Result<()> {
+        self.record(format!("git pull --rebase {} {}", remote, branch));
+        let mut repo = self.repo_mut();
+        if !repo.has_remote_branch(remote, branch) {
+            bail!(GitError::new("Failed to pull"));
+        }
+        if repo.is_upstream(remote, branch) {
+            repo.commits += repo.behind;
+            repo.behind = 0;
+        }
+        Ok(())
+    }
+
+    fn fetch(&self, remote: &str) -> Result<()> {
+        self.record(format!("git fetch {}", remote));
+        if !self.repo().remotes.iter().any(|name| name == remote) {
+            bail!(GitError::new(format!("Failed to fetch {}", remote)));
+        }
+        Ok(())
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        self.record(format!("git checkout {}", branch));
+        let mut repo = self.repo_mut();
+        if !repo.branches.iter().any(|name| name == branch) {
+            bail!(GitError::new("Failed to checkout branch"));
+        }
+        repo.branch = Some(branch.to_string());
+        repo.upstream = None;
+        repo.ahead = 0;
+        repo.behind = 0;
+        Ok(())
+    }
+
+    fn create_checkout(&self, branch: &str) -> Result<()> {
+        self.record(format!("git checkout -b {}", branch));
+        let mut repo = self.repo_mut();
+        if repo.branches.iter().any(|name| name == branch) {
+            bail!(GitError::new("Failed to checkout branch"));
+        }
+        repo.branches.push(branch.to_string());
+        repo.branch = Some(branch.to_string());
+        repo.upstream = None;
+        repo.ahead = 0;
+        repo.behind = 0;
+        Ok(())
+    }
+
+    fn operation(&self, command: &str, action: &str) -> Result<()> {
+        self.record(format!("git {} --{}", command, action));
+        let mut repo = self.repo_mut();
+        match repo.operation {
+            Some(operation) if operation.command() == command => {}
+            _ => bail!(GitError::new(format!("Failed to {} {}", action, command))),
+        }
+        if action == "continue" && repo.entries.iter().any(|entry| entry.conflicted) {
+            bail!(GitError::new(format!("Failed to {} {}", action, command)));
+        }
+        repo.operation = None;
+        Ok(())
+    }
+
+    fn bisect(&self, subcommand: &str) -> Result<()> {
+        self.record(format!("git bisect {}", subcommand));
+        let mut repo = self.repo_mut();
+        if repo.operation != Some(GitOperation::Bisect) {
+            bail!(GitError::new(format!("Failed to bisect {}", subcommand)));
+        }
+        if subcommand == "reset" {
+            repo.operation = None;
+        }
+        Ok(())
+    }
+}
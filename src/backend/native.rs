@@ -0,0 +1,329 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use git2::{BranchType, ErrorCode, Repository, Status, StatusOptions};
+
+use super::{CliBackend, GitBackend};
+use crate::{
+    git::Commit,
+    status::{BranchInfo, FileState, GitOperation, RepoSnapshot, StatusEntry},
+};
+
+/// Answers read-only queries in-process with libgit2 and leaves every
+/// mutating operation to [`CliBackend`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeBackend {
+    cli: CliBackend,
+}
+
+impl NativeBackend {
+    fn open(&self) -> Result<Option<Repository>> {
+        match Repository::discover(".") {
+            Ok(repo) if repo.is_bare() => Ok(None),
+            Ok(repo) => Ok(Some(repo)),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err).context("Failed to open repository"),
+        }
+    }
+
+    fn repo(&self) -> Result<Repository> {
+        self.open()?.context("Not in a git repository")
+    }
+}
+
+fn index_state(status: Status) -> FileState {
+    if status.is_index_new() {
+        FileState::Added
+    } else if status.is_index_modified() {
+        FileState::Modified
+    } else if status.is_index_deleted() {
+        FileState::Deleted
+    } else if status.is_index_renamed() {
+        FileState::Renamed
+    } else if status.is_index_typechange() {
+        FileState::TypeChanged
+    } else {
+        FileState::Unmodified
+    }
+}
+
+fn worktree_state(status: Status) -> FileState {
+    if status.is_wt_new() {
+        FileState::Untracked
+    } else if status.is_wt_modified() {
+        FileState::Modified
+    } else if status.is_wt_deleted() {
+        FileState::Deleted
+    } else if status.is_wt_renamed() {
+        FileState::Renamed
+    } else if status.is_wt_typechange() {
+        FileState::TypeChanged
+    } else if status.is_ignored() {
+        FileState::Ignored
+    } else {
+        FileState::Unmodified
+    }
+}
+
+impl GitBackend for NativeBackend {
+    fn init(&self) -> Result<()> {
+        self.cli.init()
+    }
+
+    fn status(&self) -> Result<Option<RepoSnapshot>> {
+        let Some(repo) = self.open()? else {
+            return Ok(None);
+        };
+        let head = repo.find_reference("HEAD")?;
+        let head_oid = head.resolve().ok().and_then(|head| head.target());
+        let mut snapshot = RepoSnapshot {
+            oid: head_oid.map(|oid| oid.to_string()),
+            // an unborn branch only exists as the symbolic target of HEAD
+            branch: head
+                .symbolic_target()
+                .and_then(|target| target.strip_prefix("refs/heads/"))
+                .map(String::from),
+            operation: GitOperation::in_git_dir(repo.path()),
+            ..Default::default()
+        };
+
+        if let (Some(branch), Some(oid)) = (&snapshot.branch, head_oid) {
+            let upstream = repo
+                .find_branch(branch, BranchType::Local)
+                .and_then(|branch| branch.upstream());
+            if let Ok(upstream) = upstream {
+                snapshot.upstream = upstream.name()?.map(String::from);
+                if let Some(upstream_oid) = upstream.get().target() {
+                    let (ahead, behind) = repo.graph_ahead_behind(oid, upstream_oid)?;
+                    (snapshot.ahead, snapshot.behind) = (ahead as u32, behind as u32);
+                }
+            }
+        }
+
+        let statuses = repo.statuses(Some(
+            StatusOptions::new()
+                .include_untracked(true)
+                .renames_head_to_index(true),
+        ))?;
+        for status in statuses.iter() {
+            let flags = status.status();
+            let Some(path) = status.path() else {
+                continue;
+            };
+            let conflicted = flags.is_conflicted();
+            // libgit2 reports a staged rename under its source path
+            let renamed = status.head_to_index().filter(|_| flags.is_index_renamed());
+            let renamed_path =
+                |file: git2::DiffFile| file.path().map(|path| path.to_string_lossy().to_string());
+            snapshot.entries.push(StatusEntry {
+                path: renamed
+                    .as_ref()
+                    .and_then(|delta| renamed_path(delta.new_file()))
+                    .unwrap_or_else(|| path.to_string()),
+                orig_path: renamed.and_then(|delta| renamed_path(delta.old_file())),
+                index: if conflicted {
+                    FileState::Unmerged
+                } else {
+                    index_state(flags)
+                },
+                worktree: if conflicted {
+                    FileState::Unmerged
+                } else {
+                    worktree_state(flags)
+                },
+                conflicted,
+                // libgit2 reports submodules as plain modified paths
+                submodule: None,
+            });
+        }
+        Ok(Some(snapshot))
+    }
+
+    fn work_tree(&self) -> Result<PathBuf> {
+        let repo = self.repo()?;
+        let dir = repo.workdir().context("The repository has no work tree")?;
+        Ok(dir.to_path_buf())
+    }
+
+    fn ahead_behind(&self, base: &str) -> Result<(u32, u32)> {
+        let repo = self.repo()?;
+        let head = repo.head()?.peel_to_commit()?.id();
+        let base = repo.revparse_single(base)?.peel_to_commit()?.id();
+        let (ahead, behind) = repo.graph_ahead_behind(head, base)?;
+        Ok((ahead as u32, behind as u32))
+    }
+
+    fn incoming_commits(&self, upstream: &str) -> Result<String> {
+        self.cli.incoming_commits(upstream)
+    }
+
+    fn diff_stat(&self, remote: &str, branch: &str) -> Result<String> {
+        self.cli.diff_stat(remote, branch)
+    }
+
+    fn stash_count(&self) -> Result<usize> {
+        self.cli.stash_count()
+    }
+
+    fn branches(&self, remote: Option<&str>) -> Result<Vec<String>> {
+        let repo = self.repo()?;
+        let branch_type = match remote {
+            Some(_) => BranchType::Remote,
+            None => BranchType::Local,
+        };
+        let mut branches = vec![];
+        for branch in repo.branches(Some(branch_type))? {
+            let (branch, _) = branch?;
+            if branch.get().symbolic_target().is_some() {
+                continue; // e.g. origin/HEAD
+            }
+            let Some(name) = branch.name()? else {
+                continue;
+            };
+            match remote {
+                Some(remote) => {
+                    if let Some(name) = name.strip_prefix(&format!("{}/", remote)) {
+                        branches.push(name.to_string());
+                    }
+                }
+                None => branches.push(name.to_string()),
+            }
+        }
+        Ok(branches)
+    }
+
+    fn branch_details(&self, remote: Option<&str>) -> Result<Vec<BranchInfo>> {
+        let repo = self.repo()?;
+        let mut branches = vec![];
+        for name in self.branches(remote)? {
+            let refname = match remote {
+                Some(remote) => format!("refs/remotes/{}/{}", remote, name),
+                None => format!("refs/heads/{}", name),
+            };
+            let commit = repo.find_reference(&refname)?.peel_to_commit()?;
+            branches.push(BranchInfo {
+                name,
+                commit: commit
+                    .as_object()
+                    .short_id()?
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                subject: commit.summary().unwrap_or_default().to_string(),
+                committed_at: commit.committer().when().seconds(),
+            });
+        }
+        branches.sort_by_key(|branch| std::cmp::Reverse(branch.committed_at));
+        Ok(branches)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        let repo = self.repo()?;
+        let remotes = repo.remotes()?;
+        Ok(remotes.iter().flatten().map(String::from).collect())
+    }
+
+    fn add_remote(&self, name: &str, url: &str) -> Result<()> {
+        self.cli.add_remote(name, url)
+    }
+
+    fn get_config(&self, key: &str) -> Result<Option<String>> {
+        self.cli.get_config(key)
+    }
+
+    fn set_config(&self, key: &str, value: &str) -> Result<()> {
+        self.cli.set_config(key, value)
+    }
+
+    fn set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.set_upstream(remote, branch)
+    }
+
+    fn add_all(&self) -> Result<()> {
+        self.cli.add_all()
+    }
+
+    fn stage(&self, paths: &[String]) -> Result<()> {
+        self.cli.stage(paths)
+    }
+
+    fn unstage(&self, paths: &[String]) -> Result<()> {
+        self.cli.unstage(paths)
+    }
+
+    fn diff_file(&self, path: &str) -> Result<String> {
+        self.cli.diff_file(path)
+    }
+
+    fn diff_staged(&self, path: &str) -> Result<String> {
+        self.cli.diff_staged(path)
+    }
+
+    fn diff_untracked(&self, path: &str) -> Result<String> {
+        self.cli.diff_untracked(path)
+    }
+
+    fn backup(&self, paths: &[String], message: &str) -> Result<()> {
+        self.cli.backup(paths, message)
+    }
+
+    fn restore(&self, paths: &[String], staged: bool) -> Result<()> {
+        self.cli.restore(paths, staged)
+    }
+
+    fn clean(&self, paths: &[String]) -> Result<()> {
+        self.cli.clean(paths)
+    }
+
+    fn apply_cached(&self, patch: &str) -> Result<()> {
+        self.cli.apply_cached(patch)
+    }
+
+    fn commit(&self, commit: &Commit) -> Result<()> {
+        self.cli.commit(commit)
+    }
+
+    fn merge(&self, branch: &str) -> Result<()> {
+        self.cli.merge(branch)
+    }
+
+    fn push(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.push(remote, branch)
+    }
+
+    fn push_set_upstream(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.push_set_upstream(remote, branch)
+    }
+
+    fn push_force_with_lease(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.push_force_with_lease(remote, branch)
+    }
+
+    fn pull(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.pull(remote, branch)
+    }
+
+    fn pull_rebase(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.pull_rebase(remote, branch)
+    }
+
+    fn fetch(&self, remote: &str) -> Result<()> {
+        self.cli.fetch(remote)
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        self.cli.checkout(branch)
+    }
+
+    fn create_checkout(&self, branch: &str) -> Result<()> {
+        self.cli.create_checkout(branch)
+    }
+
+    fn operation(&self, command: &str, action: &str) -> Result<()> {
+        self.cli.operation(command, action)
+    }
+
+    fn bisect(&self, subcommand: &str) -> Result<()> {
+        self.cli.bisect(subcommand)
+    }
+}
@@ -1,14 +1,27 @@
 use std::{
-    env,
+    env, fs,
     io::{BufRead, BufReader},
-    process::{Command, Output, Stdio},
+    path::Path,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
     thread::sleep,
     time::Duration,
 };
 
 use anyhow::{bail, Context, Result};
 
-use crate::{input::disable_raw_input, output::*};
+use crate::{git::git_dir, input::disable_raw_input, output::*};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Makes the commands that change files only say what they would do.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
 
 pub fn check_git_installed() -> Result<bool> {
     check_command_installed("git").context("Failed to check git installed")
@@ -32,30 +45,37 @@ pub fn quit() -> Result<()> {
     Ok(())
 }
 
-pub fn exec(command: &str) -> Result<Output> {
-    Command::new("sh")
-        .arg("-c")
-        .arg(command)
-        .output()
-        .context("Failed to execute command")
+/// Writes `patterns` to the .gitignore at the top of `work_tree`.
+pub fn create_gitignore(work_tree: &Path, patterns: &[String]) -> Result<()> {
+    let mut content = patterns.join("\n");
+    content.push('\n');
+    if is_dry_run() {
+        return output_notice(&format!("Would create .gitignore with:\n{}", content));
+    }
+    fs::write(work_tree.join(".gitignore"), content).context("Failed to create .gitignore")?;
+    output_success("\nCreated .gitignore.\n")
 }
 
-pub fn exec_commit(command: &str) -> Result<()> {
-    let output = exec(command)?;
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_NOTICE_FG_COLOR),
-            "\nCommand executed successfully. Output:\n".to_string(),
-        )?;
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *OUTTER_OUTPUT_FG_COLOR),
-            stdout.to_string(),
-        )
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Command execution failed: {}.", stderr);
+/// Lets the user change `text` in `editor`, a shell command like git's `core.editor`, and
+/// returns what they saved. `name` is the file in the git dir the editor opens, as git keeps
+/// `COMMIT_EDITMSG` there.
+pub fn edit_text(editor: &str, name: &str, text: &str) -> Result<String> {
+    let path = git_dir()?.join(name);
+    fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    disable_raw_input()?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to run {}", editor));
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    if !status?.success() {
+        bail!("{} exited with an error", editor);
     }
+    edited.with_context(|| format!("Failed to read {}", path.display()))
 }
 
 pub fn ai_generate_commit() -> Result<String> {
@@ -63,7 +83,7 @@ pub fn ai_generate_commit() -> Result<String> {
         bail!("AICommit is not installed.")
     }
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_NOTICE_FG_COLOR),
+        Styles::new(theme().background, theme().notice),
         "\n==> generating command by aicommit, please wait a moment ....\n".to_string(),
     )?;
     let command = execute_aicommit()?;
@@ -73,7 +93,7 @@ pub fn ai_generate_commit() -> Result<String> {
 
 pub fn execute_aicommit() -> Result<String> {
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *CODE_BORDER_FG_COLOR),
+        Styles::new(theme().background, theme().code_border),
         format!("{:-^50}\n", "AICOMMIT BEGIN".to_string()),
     )?;
     let mut child = Command::new("aicommit")
@@ -93,20 +113,23 @@ pub fn execute_aicommit() -> Result<String> {
         let content = String::from_utf8(word).context("Failed to convert word to string")?;
         full_output.push_str(&content);
 
-        colorful_print(Styles::with_bold(*CODE_BG_COLOR, *CODE_FG_COLOR), content)?;
+        colorful_print(
+            Styles::with_bold(theme().code_background, theme().code),
+            content,
+        )?;
         sleep(Duration::from_millis(300));
     }
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *CODE_BORDER_FG_COLOR),
+        Styles::new(theme().background, theme().code_border),
         "\n".to_string(),
     )?;
 
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *CODE_BORDER_FG_COLOR),
+        Styles::new(theme().background, theme().code_border),
         format!("{:-^50}", "AICOMMIT END".to_string()),
     )?;
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *CODE_BORDER_FG_COLOR),
+        Styles::new(theme().background, theme().code_border),
         "\n".to_string(),
     )?;
     let output = child.wait().context("Failed to wait on child")?;
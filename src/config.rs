@@ -0,0 +1,245 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{args::Args, backend::GitBackend, theme::Palette};
+
+/// The repository-local configuration file, looked up from the current directory to the
+/// root of the work tree.
+pub const REPO_CONFIG_FILE: &str = ".gitflow.toml";
+
+/// Settings for gitflow. Each source overrides the ones before it: the user's
+/// `~/.config/gitflow/config.toml`, the repository's `.gitflow.toml`, the `gitflow.*` keys
+/// of `git config`, then the command line. Unset fields fall back to the defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Track the configured upstream, or a remote branch with the same name, without asking.
+    pub auto_upstream: Option<bool>,
+    /// The remote used when there are several to choose from.
+    pub remote: Option<String>,
+    /// Where commit messages come from, asked every time when unset.
+    pub commit_message: Option<CommitMessage>,
+    /// A built-in theme or one of `themes`.
+    pub theme: Option<String>,
+    /// User-defined themes by name.
+    pub themes: BTreeMap<String, Palette>,
+    /// Branches that are never force pushed. A trailing `*` matches any suffix, e.g.
+    /// `release/*`.
+    pub protected_branches: Option<Vec<String>>,
+    pub keys: KeyBindings,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitMessage {
+    /// Generated by aicommit.
+    Ai,
+    /// Typed by the user.
+    Input,
+    /// Written in the editor git uses.
+    Editor,
+}
+
+/// Keys that act the same in every prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub quit: Option<char>,
+    /// Moves the highlight up when no option uses the key.
+    pub up: Option<char>,
+    /// Moves the highlight down when no option uses the key.
+    pub down: Option<char>,
+}
+
+impl KeyBindings {
+    pub fn quit(&self) -> char {
+        self.quit.unwrap_or('q').to_ascii_lowercase()
+    }
+
+    pub fn up(&self) -> char {
+        self.up.unwrap_or('k').to_ascii_lowercase()
+    }
+
+    pub fn down(&self) -> char {
+        self.down.unwrap_or('j').to_ascii_lowercase()
+    }
+
+    fn merge(&mut self, other: KeyBindings) {
+        self.quit = other.quit.or(self.quit);
+        self.up = other.up.or(self.up);
+        self.down = other.down.or(self.down);
+    }
+}
+
+impl Config {
+    /// Reads every source of the current user and directory.
+    pub fn load(git: &dyn GitBackend) -> Result<Self> {
+        Self::load_from(user_config_path().as_deref(), &env::current_dir()?, git)
+    }
+
+    /// Reads `user_file`, the `.gitflow.toml` found from `dir`, then `git config`.
+    pub fn load_from(user_file: Option<&Path>, dir: &Path, git: &dyn GitBackend) -> Result<Self> {
+        let mut config = Self::default();
+        for path in [user_file.map(Path::to_path_buf), repo_config_path(dir)]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(file) = Self::from_file(&path)? {
+                config.merge(file);
+            }
+        }
+        config.merge(Self::from_git(git)?);
+        Ok(config)
+    }
+
+    /// Parses the TOML file at `path`, `None` when there is none.
+    pub fn from_file(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text)
+            .map(Some)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))
+    }
+
+    /// Reads the `gitflow.*` keys of `git config`, e.g. `gitflow.remote` or
+    /// `gitflow.keys.quit`. Protected branches are separated by spaces or commas.
+    pub fn from_git(git: &dyn GitBackend) -> Result<Self> {
+        let get = |key: &str| git.get_config(&format!("gitflow.{}", key));
+        Ok(Self {
+            auto_upstream: get("auto-upstream")?
+                .map(|value| parse_bool("gitflow.auto-upstream", &value))
+                .transpose()?,
+            remote: get("remote")?,
+            commit_message: get("commit-message")?
+                .map(|value| match value.as_str() {
+                    "ai" => Ok(CommitMessage::Ai),
+                    "input" => Ok(CommitMessage::Input),
+                    "editor" => Ok(CommitMessage::Editor),
+                    _ => bail!(
+                        "Invalid gitflow.commit-message: {}, expected ai, input or editor",
+                        value
+                    ),
+                })
+                .transpose()?,
+            theme: get("theme")?,
+            themes: BTreeMap::new(),
+            protected_branches: get("protected-branches")?.map(|value| {
+                value
+                    .split([' ', ','])
+                    .filter(|branch| !branch.is_empty())
+                    .map(String::from)
+                    .collect()
+            }),
+            keys: KeyBindings {
+                quit: get_key(&get, "quit")?,
+                up: get_key(&get, "up")?,
+                down: get_key(&get, "down")?,
+            },
+        })
+    }
+
+    /// Lets what is set in `other` override this configuration.
+    pub fn merge(&mut self, other: Config) {
+        self.auto_upstream = other.auto_upstream.or(self.auto_upstream);
+        self.remote = other.remote.or(self.remote.take());
+        self.commit_message = other.commit_message.or(self.commit_message);
+        self.theme = other.theme.or(self.theme.take());
+        self.themes.extend(other.themes);
+        self.protected_branches = other.protected_branches.or(self.protected_branches.take());
+        self.keys.merge(other.keys);
+    }
+
+    /// Lets the flags given on the command line override this configuration.
+    pub fn merge_args(&mut self, args: &Args) {
+        if args.auto_upstream {
+            self.auto_upstream = Some(true);
+        }
+        if let Some(theme) = &args.theme {
+            self.theme = Some(theme.clone());
+        }
+    }
+
+    pub fn auto_upstream(&self) -> bool {
+        self.auto_upstream.unwrap_or(false)
+    }
+
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.protected_branches
+            .iter()
+            .flatten()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => branch.starts_with(prefix),
+                None => branch == pattern,
+            })
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    // the spellings git itself accepts
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => bail!("Invalid {}: {}, expected true or false", key, value),
+    }
+}
+
+fn get_key(get: &dyn Fn(&str) -> Result<Option<String>>, name: &str) -> Result<Option<char>> {
+    let Some(value) = get(&format!("keys.{}", name))? else {
+        return Ok(None);
+    };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) => Ok(Some(key)),
+        _ => bail!(
+            "Invalid gitflow.keys.{}: {}, expected one character",
+            name,
+            value
+        ),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/gitflow/config.toml`, `~/.config/gitflow/config.toml` by default.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_dir.join("gitflow").join("config.toml"))
+}
+
+/// The `.gitflow.toml` in `dir` or the closest parent, without leaving the work tree.
+pub fn repo_config_path(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let path = dir.join(REPO_CONFIG_FILE);
+        if path.exists() {
+            return Some(path);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+thread_local! {
+    static CURRENT: RefCell<Config> = RefCell::new(Config::default());
+}
+
+/// Makes `config` the one prompts and the flow follow.
+pub fn install(config: Config) {
+    CURRENT.with(|current| *current.borrow_mut() = config);
+}
+
+pub fn current() -> Config {
+    CURRENT.with(|current| current.borrow().clone())
+}
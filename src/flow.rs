@@ -1,72 +1,386 @@
-use std::process;
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     args::Args,
-    commands::{ai_generate_commit, exec_commit},
-    git::{self, check_in_git_repo, fetch},
-    input,
-    options::{OptionItem, Options},
-    output::{output_error, output_notice},
-    status::{GitRemoteBranch, GitStatus},
+    backend::GitBackend,
+    commands::{ai_generate_commit, create_gitignore, edit_text, quit},
+    config::{self, CommitMessage},
+    git::{self, Commit, GitError},
+    hunks::{print_diff, FileDiff, Hunk},
+    input::{self, TerminalGuard},
+    message,
+    options::{
+        Candidate, Check, CheckFilter, Checklist, Finder, Navigation, OptionItem, Options, Picker,
+    },
+    output::{emit, output_error, output_notice},
+    status::{
+        status_json, BranchInfo, FileState, GitOperation, GitRemoteBranch, GitStatus, RepoSnapshot,
+    },
 };
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+
+/// How [`run`] ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowOutcome {
+    /// The user quit.
+    Quit,
+    /// The user stopped to resolve conflicts first.
+    ConflictsPending,
+}
+
+impl FlowOutcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FlowOutcome::Quit => EXIT_QUIT,
+            FlowOutcome::ConflictsPending => EXIT_CONFLICTS,
+        }
+    }
+}
+
+impl fmt::Display for FlowOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlowOutcome::Quit => f.write_str("Quit"),
+            FlowOutcome::ConflictsPending => f.write_str("Conflicts are not resolved yet"),
+        }
+    }
+}
+
+// actions stop the flow by failing with the outcome
+impl std::error::Error for FlowOutcome {}
+
+pub const EXIT_QUIT: i32 = 0;
+/// Any failure that isn't a git command failing.
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_CONFLICTS: i32 = 2;
+pub const EXIT_GIT: i32 = 3;
 
-pub fn run(args: Args) -> Result<()> {
-    if !check_in_git_repo()? {
-        uninitialized()?;
+/// The process exit code for a flow that failed with `err`.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    let git_failed = err.chain().any(|cause| {
+        #[cfg(feature = "native")]
+        if cause.is::<git2::Error>() {
+            return true;
+        }
+        cause.is::<GitError>()
+    });
+    if git_failed {
+        EXIT_GIT
+    } else {
+        EXIT_ERROR
     }
-    let remote_info = get_upstream(args.auto_upstream)?;
+}
+
+/// Offers what to do next until the user quits. The terminal is restored however it ends.
+pub fn run(git: &dyn GitBackend, args: Args) -> Result<FlowOutcome> {
+    let _terminal = TerminalGuard;
+    // the upstream can only be resolved once HEAD points to a commit, and again after a checkout
+    let mut remote_info = None;
     loop {
-        output_notice("Checking git status...")?;
+        if let Err(err) = step(git, &args, &mut remote_info) {
+            if let Some(&outcome) = err.downcast_ref::<FlowOutcome>() {
+                return Ok(outcome);
+            }
+            match Navigation::of(&err) {
+                // there is no prompt before the first one, look at the status again
+                Some(Navigation::Back) => {}
+                Some(Navigation::Quit) => {
+                    quit()?;
+                    return Ok(FlowOutcome::Quit);
+                }
+                None => return Err(err),
+            }
+        }
+    }
+}
+
+/// The remote branch the flow compares `branch` with, once it's been looked up.
+struct RemoteInfo {
+    branch: Option<String>,
+    remote_branch: Option<GitRemoteBranch>,
+}
+
+/// Offers what can be done in the current status.
+fn step(git: &dyn GitBackend, args: &Args, remote_info: &mut Option<RemoteInfo>) -> Result<()> {
+    output_notice("Checking git status...")?;
 
-        let status = GitStatus::of(remote_info.clone()).unwrap();
-        match status {
-            GitStatus::Clean => clean()?,
-            GitStatus::Unstaged => unstaged()?,
-            GitStatus::PartiallyStaged => partially_staged()?,
-            GitStatus::FullyStaged => fully_staged()?,
-            GitStatus::PartiallyCommited => partially_committed()?,
-            GitStatus::MessPartiallyCommited => mess_partially_committed()?,
-            GitStatus::MessFullyCommited => mess_fully_committed()?,
-            GitStatus::FullyCommited => fully_committed()?,
-            GitStatus::Conflicted => conflicted()?,
+    let mut snapshot = git.status()?;
+    if let Some(snapshot) = snapshot.as_mut().filter(|snapshot| snapshot.oid.is_some()) {
+        if remote_info
+            .as_ref()
+            .is_some_and(|info| info.branch != snapshot.branch)
+        {
+            *remote_info = None;
         }
+        if remote_info.is_none() {
+            let auto_upstream = args.auto_upstream || config::current().auto_upstream();
+            *remote_info = Some(RemoteInfo {
+                branch: snapshot.branch.clone(),
+                remote_branch: get_upstream(git, snapshot, auto_upstream)?,
+            });
+        }
+        snapshot.track(
+            git,
+            remote_info
+                .as_ref()
+                .and_then(|info| info.remote_branch.as_ref()),
+        )?;
     }
+    emit("status", status_json(snapshot.as_ref()))?;
+    let menu = menu(git, snapshot.as_ref())?;
+    menu.execute()
 }
 
-fn confirm_commit(commit_command: String) -> Result<()> {
-    Options {
-        prompt: "Confirm the commit message.",
-        options: vec![
-            OptionItem {
-                key: 'Y',
-                desc: "Yes, execute it!!!".to_string(),
-                action: Box::new(|| exec_commit(&commit_command.clone())),
-            },
-            OptionItem {
-                key: 'R',
-                desc: "Regenerate commit message.".to_string(),
-                action: Box::new(commit),
-            },
-        ],
+/// Reads the repository and tracks its upstream, or else the remote branch with the same
+/// name, without asking anything.
+pub fn capture(git: &dyn GitBackend) -> Result<Option<RepoSnapshot>> {
+    let mut snapshot = git.status()?;
+    if let Some(snapshot) = snapshot.as_mut().filter(|snapshot| snapshot.oid.is_some()) {
+        let remote_branch = match (snapshot.upstream_branch(), &snapshot.branch) {
+            (Some(upstream), _) => Some(upstream),
+            (None, Some(branch)) => contain_same_branch(git, branch)?,
+            (None, None) => None,
+        };
+        snapshot.track(git, remote_branch.as_ref())?;
     }
-    .execute()
+    Ok(snapshot)
+}
+
+/// What the flow offers in a status, built without asking anything.
+pub struct Menu<'a> {
+    pub prompt: String,
+    /// Shown before the prompt, e.g. the incoming commits.
+    pub notice: Option<String>,
+    pub options: Vec<OptionItem<'a, ()>>,
+    /// The key of the recommended option, the first option's when `None`.
+    pub default: Option<char>,
+}
+
+impl<'a> Menu<'a> {
+    fn new(prompt: impl Into<String>, options: Vec<OptionItem<'a, ()>>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            notice: None,
+            options,
+            default: None,
+        }
+    }
+
+    fn with_default(mut self, key: char) -> Self {
+        self.default = Some(key);
+        self
+    }
+
+    fn with_notice(mut self, notice: String) -> Self {
+        self.notice = Some(notice);
+        self
+    }
+
+    /// The recommended option, if there is any option at all.
+    pub fn recommended(&self) -> Option<&OptionItem<'a, ()>> {
+        self.default
+            .and_then(|key| self.options.iter().find(|option| option.key == key))
+            .or(self.options.first())
+    }
+
+    pub fn execute(self) -> Result<()> {
+        if let Some(notice) = &self.notice {
+            output_notice(notice)?;
+        }
+        let default = self.recommended().map_or('q', |option| option.key);
+        Options {
+            prompt: &self.prompt,
+            options: self.options,
+        }
+        .execute_with_default(default)
+    }
+}
+
+/// The menu of the status `snapshot` is in, `None` when not in a git repository.
+pub fn menu<'a>(git: &'a dyn GitBackend, snapshot: Option<&'a RepoSnapshot>) -> Result<Menu<'a>> {
+    let Some(snapshot) = snapshot else {
+        return Ok(uninitialized(git));
+    };
+    Ok(match GitStatus::of(Some(snapshot)) {
+        GitStatus::Uninitialized => uninitialized(git),
+        GitStatus::Initialized => initialized(git, snapshot)?,
+        GitStatus::Clean => clean(git),
+        GitStatus::Unstaged => unstaged(git, snapshot),
+        GitStatus::PartiallyStaged => partially_staged(git, snapshot),
+        GitStatus::FullyStaged => fully_staged(git, snapshot),
+        GitStatus::PartiallyCommited => partially_committed(git, snapshot),
+        GitStatus::MessPartiallyCommited => mess_partially_committed(git),
+        GitStatus::MessFullyCommited => mess_fully_committed(git),
+        GitStatus::FullyCommited => fully_committed(git, snapshot),
+        GitStatus::UpToDate => up_to_date(git, snapshot)?,
+        GitStatus::Behind => behind(git, snapshot)?,
+        GitStatus::Diverged => diverged(git, snapshot)?,
+        GitStatus::Conflicted => conflicted(git),
+        GitStatus::Merging => in_progress(git, GitOperation::Merge),
+        GitStatus::Rebasing => in_progress(git, GitOperation::Rebase),
+        GitStatus::CherryPicking => in_progress(git, GitOperation::CherryPick),
+        GitStatus::Reverting => in_progress(git, GitOperation::Revert),
+        GitStatus::Bisecting => bisecting(git),
+        GitStatus::Applying => in_progress(git, GitOperation::Am),
+    })
+}
+
+/// What to do with a commit before making it.
+#[derive(Clone, Copy)]
+enum CommitChoice {
+    Confirm,
+    Rewrite,
+    Edit,
+    Amend,
+    Signoff,
+    NoVerify,
+}
+
+/// Shows `commit` and lets the user change its options until they confirm it.
+fn confirm_commit(git: &dyn GitBackend, mut commit: Commit) -> Result<()> {
+    loop {
+        output_notice(&format!("\n{}\n", commit))?;
+        let option = |key, desc: &str, choice| OptionItem {
+            key,
+            desc: desc.to_string(),
+            action: Box::new(move || Ok(choice)),
+        };
+        let toggle =
+            |enabled: bool, on: &'static str, off: &'static str| if enabled { off } else { on };
+        let choice = Options {
+            prompt: "Confirm the commit.",
+            options: vec![
+                option('Y', "Yes, commit it!", CommitChoice::Confirm),
+                option('R', "Regenerate commit message.", CommitChoice::Rewrite),
+                option('E', "Edit the message in the editor.", CommitChoice::Edit),
+                option(
+                    'M',
+                    toggle(
+                        commit.amend,
+                        "Amend the last commit instead.",
+                        "Make a new commit instead.",
+                    ),
+                    CommitChoice::Amend,
+                ),
+                option(
+                    'S',
+                    toggle(commit.signoff, "Sign it off.", "Don't sign it off."),
+                    CommitChoice::Signoff,
+                ),
+                option(
+                    'N',
+                    toggle(
+                        commit.no_verify,
+                        "Skip the commit hooks.",
+                        "Run the commit hooks.",
+                    ),
+                    CommitChoice::NoVerify,
+                ),
+            ],
+        }
+        .execute()?;
+        match choice {
+            CommitChoice::Confirm => return git.commit(&commit),
+            CommitChoice::Rewrite => return self::commit(git),
+            CommitChoice::Edit => commit.message = edit_message(git, Some(&commit.message))?,
+            CommitChoice::Amend => commit.amend = !commit.amend,
+            CommitChoice::Signoff => commit.signoff = !commit.signoff,
+            CommitChoice::NoVerify => commit.no_verify = !commit.no_verify,
+        }
+    }
+}
+
+/// Asks for the commit message, a single line.
+fn input_commit() -> Result<Commit> {
+    input::read_line("Input the commit message:").map(Commit::new)
+}
+
+/// Writes the commit message in the editor git uses, starting from `suggestion` and
+/// `commit.template`, with the staged files listed below as comments.
+fn edit_message(git: &dyn GitBackend, suggestion: Option<&str>) -> Result<String> {
+    let template = match git.get_config("commit.template")? {
+        Some(path) => {
+            // git expands a leading ~ in paths it reads from its configuration
+            let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => path.into(),
+            };
+            Some(fs::read_to_string(&path).with_context(|| {
+                format!("Failed to read the commit template {}", path.display())
+            })?)
+        }
+        None => None,
+    };
+    let snapshot = git.status()?;
+    let staged: Vec<_> = snapshot
+        .iter()
+        .flat_map(|snapshot| &snapshot.entries)
+        .filter(|entry| entry.is_staged())
+        .collect();
+    let start = match (suggestion, template.as_deref()) {
+        (Some(suggestion), Some(template)) => message::with_template(suggestion, template),
+        (suggestion, template) => suggestion.or(template).unwrap_or_default().to_string(),
+    };
+    let text = edit_text(
+        &git::editor()?,
+        "COMMIT_EDITMSG",
+        &message::template(&start, &staged),
+    )?;
+    let edited = message::strip(&text);
+    if edited.is_empty() {
+        bail!("Aborting commit due to empty commit message.");
+    }
+    if suggestion.is_none() && template.is_some_and(|template| message::strip(&template) == edited)
+    {
+        bail!("Aborting commit; you did not edit the message from the template.");
+    }
+    Ok(edited)
+}
+
+/// Takes the message from the command aicommit suggests.
+fn ai_commit() -> Result<Commit> {
+    ai_generate_commit().and_then(|command| Commit::parse(&command))
 }
 
-fn commit() -> Result<()> {
+fn commit(git: &dyn GitBackend) -> Result<()> {
+    match config::current().commit_message {
+        Some(CommitMessage::Ai) => {
+            return ai_commit().and_then(|commit| confirm_commit(git, commit))
+        }
+        Some(CommitMessage::Input) => {
+            return input_commit().and_then(|commit| confirm_commit(git, commit))
+        }
+        Some(CommitMessage::Editor) => {
+            return edit_message(git, None)
+                .and_then(|message| confirm_commit(git, Commit::new(message)))
+        }
+        None => {}
+    }
     Options {
         prompt: "Choose a way to commit.",
         options: vec![
             OptionItem {
                 key: 'A',
                 desc: "AI generate commit message.".to_string(),
-                action: Box::new(|| ai_generate_commit().and_then(confirm_commit)),
+                action: Box::new(|| ai_commit().and_then(|commit| confirm_commit(git, commit))),
             },
             OptionItem {
                 key: 'I',
                 desc: "Input commit message.".to_string(),
+                action: Box::new(|| input_commit().and_then(|commit| confirm_commit(git, commit))),
+            },
+            OptionItem {
+                key: 'E',
+                desc: "Write the commit message in the editor.".to_string(),
                 action: Box::new(|| {
-                    input::read_line("Inpput commit command").and_then(confirm_commit)
+                    edit_message(git, None)
+                        .and_then(|message| confirm_commit(git, Commit::new(message)))
                 }),
             },
         ],
@@ -74,107 +388,206 @@ fn commit() -> Result<()> {
     .execute()
 }
 
-fn unstaged() -> Result<()> {
-    Options {
-        prompt: "Files unstaged.",
-        options: vec![
+fn unstaged<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Menu<'a> {
+    Menu::new(
+        "Files unstaged.",
+        vec![
             OptionItem {
                 key: 'A',
                 desc: "Add all files.".to_string(),
-                action: Box::new(add),
+                action: Box::new(move || add(git)),
+            },
+            OptionItem {
+                key: 'S',
+                desc: "Stage some files.".to_string(),
+                action: Box::new(move || stage_files(git, snapshot)),
+            },
+            OptionItem {
+                key: 'P',
+                desc: "Review and stage hunks.".to_string(),
+                action: Box::new(move || stage_hunks(git, snapshot)),
+            },
+            OptionItem {
+                key: 'D',
+                desc: "Discard changes to some files.".to_string(),
+                action: Box::new(move || discard_files(git, snapshot, false)),
             },
             OptionItem {
                 key: 'O',
                 desc: "Checkout a branch.".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
             OptionItem {
                 key: 'B',
                 desc: "Create a branch.".to_string(),
-                action: Box::new(create_branch),
+                action: Box::new(move || create_branch(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn uninitialized() -> Result<()> {
-    Options {
-        prompt: "Not in a git repository, do you wanna initialize git repo?",
-        options: vec![OptionItem {
+fn uninitialized(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "Not in a git repository, do you wanna initialize git repo?",
+        vec![OptionItem {
             key: 'Y',
             desc: "Yes, initialize.".to_string(),
-            action: Box::new(git::init),
+            action: Box::new(move || git.init()),
         }],
+    )
+}
+
+fn initialized<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Result<Menu<'a>> {
+    let mut options = vec![];
+    if git.get_config("user.name")?.is_none() || git.get_config("user.email")?.is_none() {
+        options.push(OptionItem {
+            key: 'I',
+            desc: "Configure user name and email.".to_string(),
+            action: Box::new(move || configure_identity(git)),
+        });
     }
-    .execute()
+    let work_tree = git.work_tree()?;
+    if !work_tree.join(".gitignore").exists() {
+        options.push(OptionItem {
+            key: 'G',
+            desc: "Create a .gitignore.".to_string(),
+            action: Box::new(move || gitignore(&work_tree)),
+        });
+    }
+    if snapshot.has_unstaged() {
+        options.push(OptionItem {
+            key: 'A',
+            desc: "Add all files.".to_string(),
+            action: Box::new(move || add(git)),
+        });
+    }
+    if snapshot.has_staged() {
+        options.push(OptionItem {
+            key: 'C',
+            desc: "Make the first commit.".to_string(),
+            action: Box::new(move || first_commit(git, snapshot)),
+        });
+    }
+    Ok(Menu::new(
+        "No commits yet, let's make the first commit:",
+        options,
+    ))
+}
+
+fn configure_identity(git: &dyn GitBackend) -> Result<()> {
+    let name = input::read_line("Input user name:")?;
+    git.set_config("user.name", &name)?;
+    let email = input::read_line("Input user email:")?;
+    git.set_config("user.email", &email)
+}
+
+fn gitignore(work_tree: &Path) -> Result<()> {
+    input::read_line("Input patterns to ignore, separated by spaces:").and_then(|patterns| {
+        create_gitignore(
+            work_tree,
+            &patterns
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>(),
+        )
+    })
 }
 
-fn clean() -> Result<()> {
+fn first_commit(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    commit(git)?;
     Options {
-        prompt: "Nothing to commit, working tree clean. Maybe you wanna:",
+        prompt: "First commit created, do you wanna publish it to a remote?",
         options: vec![
+            OptionItem {
+                key: 'Y',
+                desc: "Yes, push it and set upstream.".to_string(),
+                action: Box::new(|| push_set_upstream(git, snapshot)),
+            },
+            OptionItem {
+                key: 'N',
+                desc: "No, maybe later.".to_string(),
+                action: Box::new(|| Ok(())),
+            },
+        ],
+    }
+    .execute()
+}
+
+fn push_set_upstream(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    let remotes = git.remotes()?;
+    let remote = match remotes.len() {
+        0 => add_remote(git)?,
+        1 => remotes[0].clone(),
+        _ => choose_remote(remotes)?,
+    };
+    let branch = snapshot.branch.as_ref().context("HEAD is detached")?;
+    output_notice("\nPushing, please wait a moment...\n")?;
+    git.push_set_upstream(&remote, branch)
+}
+
+fn clean(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "Nothing to commit, working tree clean. Maybe you wanna:",
+        vec![
             OptionItem {
                 key: 'M',
                 desc: "Merge another branch".to_string(),
-                action: Box::new(merge),
+                action: Box::new(move || merge(git)),
             },
             OptionItem {
                 key: 'C',
                 desc: "Checkout a branch.".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
             OptionItem {
                 key: 'B',
                 desc: "Create a branch.".to_string(),
-                action: Box::new(create_branch),
+                action: Box::new(move || create_branch(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn merge() -> Result<()> {
+fn merge(git: &dyn GitBackend) -> Result<()> {
     Options {
         prompt: "Merge local branch or remote branch",
         options: vec![
             OptionItem {
                 key: 'L',
                 desc: "Merge local branch".to_string(),
-                action: Box::new(merge_local_branch),
+                action: Box::new(|| merge_local_branch(git)),
             },
             OptionItem {
                 key: 'R',
                 desc: "Merge remote branch".to_string(),
-                action: Box::new(merge_remote_branch),
+                action: Box::new(|| merge_remote_branch(git)),
             },
         ],
     }
     .execute()
 }
 
-fn pull() -> Result<()> {
-    let upstream = git::get_upstream()?;
-    if let Some(upstream) = upstream {
+fn pull(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    if let Some(upstream) = snapshot.upstream_branch() {
         Options {
             prompt: &format!(
-                "There is an upstream: {}, do you wanna pull from it?",
-                upstream
+                "There is an upstream: {}/{}, do you wanna pull from it?",
+                upstream.remote, upstream.branch
             ),
             options: vec![OptionItem {
                 key: 'Y',
                 desc: "Yes, pull from upstream branch".to_string(),
-                action: Box::new(|| git::pull(None)),
+                action: Box::new(move || git.pull(&upstream.remote, &upstream.branch)),
             }],
         }
         .execute()
     } else {
-        select_pull()
+        select_pull(git)
     }
 }
 
-fn select_pull() -> Result<()> {
-    let remotes = git::get_remote_names()?;
+fn select_pull(git: &dyn GitBackend) -> Result<()> {
+    let remotes = git.remotes()?;
     if remotes.is_empty() {
         output_notice("No remote found, need add one first")
     } else if remotes.len() == 1 {
@@ -184,209 +597,537 @@ fn select_pull() -> Result<()> {
                 key: 'Y',
                 desc: "Yea, use this".to_string(),
                 action: Box::new(|| {
-                    let branch =
-                        git::get_branches(remotes.first().cloned()).and_then(choose_branch)?;
-                    git::pull(Some((&remotes[0], &branch)))
+                    let branch = choose_branch(git, Some(&remotes[0]))?;
+                    git.pull(&remotes[0], &branch)
                 }),
             }],
         }
         .execute()
     } else {
         choose_remote(remotes).and_then(|remote| {
-            let branch = git::get_branches(Some(remote.clone())).and_then(choose_branch)?;
-            git::pull(Some((&remote, &branch)))
+            let branch = choose_branch(git, Some(&remote))?;
+            git.pull(&remote, &branch)
         })
     }
 }
 
-fn merge_local_branch() -> Result<()> {
-    git::get_branches(None).and_then(|branches| {
-        Options {
-            prompt: "Please choose a branch.",
-            options: branches
-                .into_iter()
-                .enumerate()
-                .map(|(idx, branch_name)| {
-                    let branch_name = branch_name.clone();
-                    OptionItem {
-                        key: std::char::from_u32(idx as u32).unwrap(),
-                        desc: branch_name.clone(),
-                        action: Box::new(move || git::merge(&branch_name)),
-                    }
-                })
-                .collect(),
-        }
-        .execute()
-    })
+fn merge_local_branch(git: &dyn GitBackend) -> Result<()> {
+    choose_branch(git, None).and_then(|branch| git.merge(&branch))
 }
 
-fn merge_remote_branch() -> Result<()> {
-    select_remote_branch()
-        .and_then(|(remote, branch)| git::merge(&format!("{}/{}", remote, branch)))
+fn merge_remote_branch(git: &dyn GitBackend) -> Result<()> {
+    select_remote_branch(git)
+        .and_then(|(remote, branch)| git.merge(&format!("{}/{}", remote, branch)))
 }
 
-fn add() -> Result<()> {
+fn add(git: &dyn GitBackend) -> Result<()> {
     Options {
         prompt: "Confirm to add all?",
         options: vec![OptionItem {
             key: 'Y',
             desc: "Yes, add all!".to_string(),
-            action: Box::new(git::add_all),
+            action: Box::new(|| git.add_all()),
         }],
     }
     .execute()
 }
 
-fn checkout_branch() -> Result<()> {
-    git::get_branches(None)
-        .and_then(choose_branch)
-        .and_then(|branch| git::checkout(&branch))
+/// Stages the files the user checks.
+fn stage_files(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    let paths = Checklist {
+        prompt: "Select the files to stage.",
+        items: snapshot
+            .entries
+            .iter()
+            .filter(|entry| entry.is_unstaged())
+            .map(|entry| Check {
+                value: entry.path.clone(),
+                label: entry.worktree.describe().to_string(),
+            })
+            .collect(),
+        filters: vec![
+            CheckFilter {
+                key: 'm',
+                label: "modified",
+            },
+            CheckFilter {
+                key: 'u',
+                label: "untracked",
+            },
+            CheckFilter {
+                key: 'd',
+                label: "deleted",
+            },
+        ],
+    }
+    .select()?;
+    git.stage(&paths)
+}
+
+/// What to do with a hunk under review.
+#[derive(Clone, Copy)]
+enum HunkChoice {
+    Stage,
+    Skip,
+    /// Stage this hunk and the rest of the file.
+    StageRest,
+    /// Skip this hunk and the rest of the file.
+    SkipRest,
+    Split,
+    Edit,
+}
+
+const EDIT_HUNK_HELP: &str = "\
+# To leave a removed line in, turn its '-' into ' '.
+# To leave an added line out, delete it.
+# Lines starting with # are ignored.
+";
+
+/// Walks through the hunks of every tracked file with unstaged changes, like `git add -p`,
+/// and stages the ones the user picks.
+fn stage_hunks(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    let paths: Vec<_> = snapshot
+        .entries
+        .iter()
+        .filter(|entry| entry.is_unstaged() && entry.worktree != FileState::Untracked)
+        .map(|entry| entry.path.clone())
+        .collect();
+    if paths.is_empty() {
+        return output_notice("\nNo tracked file has unstaged changes.\n");
+    }
+    for path in paths {
+        let Some(diff) = FileDiff::parse(&git.diff_file(&path)?)? else {
+            continue;
+        };
+        let (hunks, edited) = review_hunks(&path, &diff)?;
+        if hunks.is_empty() {
+            continue;
+        }
+        match git.apply_cached(&diff.patch(&hunks)) {
+            // an edited hunk may not apply, which shouldn't cost the other files
+            Err(err) if edited => output_error(&format!("\n{:#}\n", err))?,
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Asks about each hunk of `diff`, returning the ones to stage and whether any was edited.
+fn review_hunks(path: &str, diff: &FileDiff) -> Result<(Vec<Hunk>, bool)> {
+    let mut pending: VecDeque<Hunk> = diff.hunks.iter().cloned().collect();
+    let mut staged = vec![];
+    let mut edited = false;
+    let mut reviewed = 0;
+    while let Some(hunk) = pending.pop_front() {
+        reviewed += 1;
+        let count = reviewed + pending.len();
+        emit(
+            "hunk",
+            json!({
+                "path": path,
+                "index": reviewed,
+                "count": count,
+                "header": hunk.header(),
+                "lines": hunk.lines,
+            }),
+        )?;
+        hunk.print(&format!("{} ({}/{})", path, reviewed, count))?;
+        let prompt = format!("Stage this hunk of {}?", path);
+        let option = |key, desc: &str, choice| OptionItem {
+            key,
+            desc: desc.to_string(),
+            action: Box::new(move || Ok(choice)),
+        };
+        let mut options = vec![
+            option('Y', "Stage this hunk.", HunkChoice::Stage),
+            option('N', "Skip this hunk.", HunkChoice::Skip),
+            option(
+                'A',
+                "Stage this hunk and the rest of the file.",
+                HunkChoice::StageRest,
+            ),
+            option(
+                'D',
+                "Skip this hunk and the rest of the file.",
+                HunkChoice::SkipRest,
+            ),
+        ];
+        if hunk.can_split() {
+            options.push(option(
+                'S',
+                "Split it into smaller hunks.",
+                HunkChoice::Split,
+            ));
+        }
+        options.push(option('E', "Edit it, then stage it.", HunkChoice::Edit));
+        let choice = Options {
+            prompt: &prompt,
+            options,
+        }
+        .execute()?;
+        match choice {
+            HunkChoice::Stage => staged.push(hunk),
+            HunkChoice::Skip => {}
+            HunkChoice::StageRest => {
+                staged.push(hunk);
+                staged.extend(pending.drain(..));
+            }
+            HunkChoice::SkipRest => break,
+            HunkChoice::Split => {
+                reviewed -= 1;
+                for part in hunk.split().into_iter().rev() {
+                    pending.push_front(part);
+                }
+            }
+            HunkChoice::Edit => {
+                let text = edit_text(
+                    &git::editor()?,
+                    "addp-hunk-edit.diff",
+                    &format!("{}{}", hunk, EDIT_HUNK_HELP),
+                )?;
+                // the header is recomputed from the edited lines
+                let text: String = text
+                    .lines()
+                    .skip_while(|line| line.starts_with("@@"))
+                    .map(|line| format!("{}\n", line))
+                    .collect();
+                match hunk.edited(&text) {
+                    Ok(hunk) => {
+                        staged.push(hunk);
+                        edited = true;
+                    }
+                    // e.g. an edit with no changes left, the hunk is asked about again
+                    Err(err) => {
+                        output_error(&format!("\n{:#}\n", err))?;
+                        reviewed -= 1;
+                        pending.push_front(hunk);
+                    }
+                }
+            }
+        }
+    }
+    Ok((staged, edited))
+}
+
+/// Takes the files the user checks out of the index.
+fn unstage_files(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    let staged: Vec<_> = snapshot
+        .entries
+        .iter()
+        .filter(|entry| entry.is_staged())
+        .collect();
+    let chosen = Checklist {
+        prompt: "Select the files to unstage.",
+        items: staged
+            .iter()
+            .map(|entry| Check {
+                value: entry.path.clone(),
+                label: entry.index.describe().to_string(),
+            })
+            .collect(),
+        filters: vec![
+            CheckFilter {
+                key: 'm',
+                label: "modified",
+            },
+            CheckFilter {
+                key: 'n',
+                label: "new file",
+            },
+            CheckFilter {
+                key: 'd',
+                label: "deleted",
+            },
+        ],
+    }
+    .select()?;
+    // a rename is only undone with its source path
+    let paths: Vec<_> = staged
+        .iter()
+        .filter(|entry| chosen.contains(&entry.path))
+        .flat_map(|entry| [Some(&entry.path), entry.orig_path.as_ref()])
+        .flatten()
+        .cloned()
+        .collect();
+    git.unstage(&paths)
+}
+
+/// Throws away the changes to the files the user checks, the unstaged ones or, when `staged`,
+/// everything since HEAD. What's lost is shown first and backed up before it goes.
+fn discard_files(git: &dyn GitBackend, snapshot: &RepoSnapshot, staged: bool) -> Result<()> {
+    let entries: Vec<_> = snapshot
+        .entries
+        .iter()
+        .filter(|entry| {
+            if staged {
+                entry.is_staged()
+            } else {
+                entry.is_unstaged()
+            }
+        })
+        .collect();
+    let filter = |key, label| CheckFilter { key, label };
+    let chosen = Checklist {
+        prompt: "Select the files to discard the changes to.",
+        items: entries
+            .iter()
+            .map(|entry| Check {
+                value: entry.path.clone(),
+                label: if staged { entry.index } else { entry.worktree }
+                    .describe()
+                    .to_string(),
+            })
+            .collect(),
+        filters: if staged {
+            vec![
+                filter('m', "modified"),
+                filter('n', "new file"),
+                filter('d', "deleted"),
+            ]
+        } else {
+            vec![
+                filter('m', "modified"),
+                filter('u', "untracked"),
+                filter('d', "deleted"),
+            ]
+        },
+    }
+    .select()?;
+    let chosen: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| chosen.contains(&entry.path))
+        .collect();
+
+    let mut tracked = vec![];
+    let mut untracked = vec![];
+    let mut preview = String::new();
+    for entry in chosen {
+        if entry.worktree == FileState::Untracked {
+            preview.push_str(&git.diff_untracked(&entry.path)?);
+            untracked.push(entry.path.clone());
+        } else if staged {
+            // a rename is only undone by bringing its source back too
+            for path in [Some(&entry.path), entry.orig_path.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                preview.push_str(&git.diff_staged(path)?);
+                tracked.push(path.clone());
+            }
+        } else {
+            preview.push_str(&git.diff_file(&entry.path)?);
+            tracked.push(entry.path.clone());
+        }
+    }
+    let paths: Vec<_> = tracked.iter().chain(&untracked).cloned().collect();
+    emit("discard", json!({ "paths": paths, "diff": preview }))?;
+    output_notice("\nThese changes will be lost:\n")?;
+    print_diff(&preview)?;
+
+    Options {
+        prompt: "Discard them? They are backed up first.",
+        options: vec![OptionItem {
+            key: 'Y',
+            desc: "Yes, discard them.".to_string(),
+            action: Box::new(|| {
+                git.backup(&paths, "gitflow: changes discarded")?;
+                if !tracked.is_empty() {
+                    git.restore(&tracked, staged)?;
+                }
+                if !untracked.is_empty() {
+                    git.clean(&untracked)?;
+                }
+                output_notice(&format!(
+                    "\nDiscarded, bring a file back with: git restore --source={} -- <file>\n",
+                    git::BACKUP_REF
+                ))
+            }),
+        }],
+    }
+    .execute()?;
+    Ok(())
+}
+
+fn checkout_branch(git: &dyn GitBackend) -> Result<()> {
+    choose_branch(git, None).and_then(|branch| git.checkout(&branch))
 }
 
-fn choose_branch(branches: Vec<String>) -> Result<String> {
+fn choose_branch(git: &dyn GitBackend, remote: Option<&str>) -> Result<String> {
+    let branches = git.branch_details(remote)?;
     if branches.len() == 1 {
         Options {
-            prompt: &format!("Only one branch found: {}", branches[0]),
+            prompt: &format!("Only one branch found: {}", branches[0].name),
             options: vec![OptionItem {
                 key: 'Y',
                 desc: "Yes, use this branch.".to_string(),
-                action: Box::new(|| Ok(branches[0].clone())),
+                action: Box::new(|| Ok(branches[0].name.clone())),
             }],
         }
         .execute()
     } else {
-        Options {
-            prompt: "Please choose a branch.",
-            options: branches
-                .into_iter()
-                .enumerate()
-                .map(|(idx, branch_name)| {
-                    let branch_name = branch_name.clone();
-                    OptionItem {
-                        key: index_to_char(idx),
-                        desc: branch_name.clone(),
-                        action: Box::new(move || Ok(branch_name.clone())),
-                    }
-                })
-                .collect(),
+        Finder {
+            prompt: "Type to find a branch.",
+            candidates: branches.into_iter().map(branch_candidate).collect(),
         }
-        .execute()
+        .find()
+    }
+}
+
+fn branch_candidate(branch: BranchInfo) -> Candidate {
+    let preview = (!branch.commit.is_empty()).then(|| {
+        format!(
+            "{} {} ({})",
+            branch.commit,
+            branch.subject,
+            relative_age(branch.committed_at)
+        )
+    });
+    Candidate {
+        value: branch.name,
+        preview,
+        recency: branch.committed_at,
     }
 }
 
+/// Describes how long ago `timestamp` was, e.g. "3 days ago".
+fn relative_age(timestamp: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    let seconds = (now - timestamp).max(0);
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        86400..=2591999 => (seconds / 86400, "day"),
+        2592000..=31535999 => (seconds / 2592000, "month"),
+        _ => (seconds / 31536000, "year"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Picks the configured remote when there is one, else asks.
 fn choose_remote(remotes: Vec<String>) -> Result<String> {
-    Options {
+    if let Some(remote) = config::current()
+        .remote
+        .filter(|remote| remotes.contains(remote))
+    {
+        return Ok(remote);
+    }
+    Picker {
         prompt: "Select a remote.",
-        options: remotes
-            .into_iter()
-            .enumerate()
-            .map(|(idx, remote_name)| OptionItem {
-                key: index_to_char(idx),
-                desc: remote_name.clone(),
-                action: Box::new(move || Ok(remote_name.clone())),
-            })
-            .collect(),
+        items: remotes,
     }
-    .execute()
+    .pick()
 }
-fn create_branch() -> Result<()> {
+
+fn create_branch(git: &dyn GitBackend) -> Result<()> {
     input::read_line("Please input the branch name:")
-        .and_then(|branch_name| git::create_checkout(&branch_name))
+        .and_then(|branch_name| git.create_checkout(&branch_name))
 }
 
-fn get_upstream(auto_upstream: bool) -> Result<Option<GitRemoteBranch>> {
-    let upstream = git::get_upstream()?;
-    if let Some(upstream) = upstream {
+fn get_upstream(
+    git: &dyn GitBackend,
+    snapshot: &RepoSnapshot,
+    auto_upstream: bool,
+) -> Result<Option<GitRemoteBranch>> {
+    let Some(local_branch) = &snapshot.branch else {
+        return Ok(None);
+    };
+    if let Some(upstream) = snapshot.upstream_branch() {
         if auto_upstream {
-            let sp: Vec<_> = upstream.splitn(2, "/").collect();
-            return Ok(Some(GitRemoteBranch {
-                remote: sp[0].to_string(),
-                branch: sp[1].to_string(),
-            }));
+            return Ok(Some(upstream));
         }
         Options {
-            prompt: &format!("There is an upstream: {}, do you wanna use it?", upstream),
+            prompt: &format!(
+                "There is an upstream: {}/{}, do you wanna use it?",
+                upstream.remote, upstream.branch
+            ),
             options: vec![
                 OptionItem {
                     key: 'Y',
                     desc: "Yes, use upstream.".to_string(),
-                    action: Box::new(|| {
-                        let sp: Vec<_> = upstream.splitn(2, "/").collect();
-                        Ok(Some(GitRemoteBranch {
-                            remote: sp[0].to_string(),
-                            branch: sp[1].to_string(),
-                        }))
-                    }),
+                    action: Box::new(move || Ok(Some(upstream.clone()))),
                 },
                 OptionItem {
                     key: 'N',
                     desc: "No, use another remote.".to_string(),
-                    action: Box::new(set_upstream),
+                    action: Box::new(|| set_upstream(git, local_branch)),
                 },
             ],
         }
         .execute()
     } else {
         if auto_upstream {
-            if let Some(remote_branch) = contain_same_branch()? {
+            if let Some(remote_branch) = contain_same_branch(git, local_branch)? {
                 return Ok(Some(remote_branch));
             }
         }
-        set_upstream()
+        set_upstream(git, local_branch)
     }
 }
 
-fn contain_same_branch() -> Result<Option<GitRemoteBranch>> {
-    let local_branch = git::get_current_branch()?;
-    let remotes = git::get_remote_names()?;
+fn contain_same_branch(
+    git: &dyn GitBackend,
+    local_branch: &str,
+) -> Result<Option<GitRemoteBranch>> {
+    let mut remotes = git.remotes()?;
     if remotes.is_empty() {
         return Ok(None);
     }
+    // the preferred remote goes first
+    if let Some(preferred) = config::current().remote {
+        remotes.sort_by_key(|remote| *remote != preferred);
+    }
     for remote in remotes {
-        let branches = git::get_branches(Some(remote.clone()))?;
-        if branches.contains(&local_branch) {
+        let branches = git.branches(Some(&remote))?;
+        if branches.iter().any(|branch| branch == local_branch) {
             return Ok(Some(GitRemoteBranch {
                 remote: remote.clone(),
-                branch: local_branch.clone(),
+                branch: local_branch.to_string(),
             }));
         }
     }
     Ok(None)
 }
 
-fn set_upstream() -> Result<Option<GitRemoteBranch>> {
-    let local_branch = git::get_current_branch()?;
-    let remotes = git::get_remote_names()?;
+fn set_upstream(git: &dyn GitBackend, local_branch: &str) -> Result<Option<GitRemoteBranch>> {
+    let remotes = git.remotes()?;
     let remote = match remotes.len() {
-        0 => add_remote().and_then(|remote| {
-            fetch(&remote)?;
+        0 => add_remote(git).and_then(|remote| {
+            git.fetch(&remote)?;
             Ok(remote)
         })?,
         1 => remotes[0].clone(),
-        _ => choose_remote(remotes.clone())?,
+        _ => choose_remote(remotes)?,
     };
 
-    let branches = git::get_branches(Some(remote.clone()))?;
-    if branches.contains(&local_branch) {
+    let branches = git.branches(Some(&remote))?;
+    if branches.iter().any(|branch| branch == local_branch) {
         Options {
                     prompt: &format!(
                         "There is a remote branch that has the same name with local branch, do you wanna set upstream to it: {}/{}?",
-                        remote.clone(), &local_branch
+                        remote.clone(), local_branch
                     ),
                     options: vec![
                         OptionItem {
                             key: 'Y',
                             desc: "Yes, set upstream.".to_string(),
-                            action: Box::new(|| git::set_upstream(&remotes[0], &local_branch).map(|()| Some(GitRemoteBranch{
-                                remote: remotes[0].clone(),
-                                branch: local_branch.clone(),
+                            action: Box::new(|| git.set_upstream(&remote, local_branch).map(|()| Some(GitRemoteBranch{
+                                remote: remote.clone(),
+                                branch: local_branch.to_string(),
                             }))),
                         },
 
                         OptionItem {
                             key: 'N',
                             desc: "No, use another branch.".to_string(),
-                            action: Box::new(||choose_branch(branches.clone()).map(|branch| Some(GitRemoteBranch{
+                            action: Box::new(||choose_branch(git, Some(&remote)).map(|branch| Some(GitRemoteBranch{
                                 remote: remote.clone(),
                                 branch: branch.clone(),
                             }))),
@@ -398,8 +1139,8 @@ fn set_upstream() -> Result<Option<GitRemoteBranch>> {
     }
 }
 
-fn select_remote_branch() -> Result<(String, String)> {
-    let remotes = git::get_remote_names()?;
+fn select_remote_branch(git: &dyn GitBackend) -> Result<(String, String)> {
+    let remotes = git.remotes()?;
     if remotes.is_empty() {
         Options {
             prompt: "No remote found. Do you wanna add a new remote?",
@@ -407,10 +1148,9 @@ fn select_remote_branch() -> Result<(String, String)> {
                 key: 'Y',
                 desc: "Yea, add a new remote.".to_string(),
                 action: Box::new(|| {
-                    add_remote().and_then(|remote| {
-                        git::fetch(&remote)?;
-                        let branch =
-                            git::get_branches(Some(remote.clone())).and_then(choose_branch)?;
+                    add_remote(git).and_then(|remote| {
+                        git.fetch(&remote)?;
+                        let branch = choose_branch(git, Some(&remote))?;
                         Ok((remote, branch))
                     })
                 }),
@@ -424,8 +1164,7 @@ fn select_remote_branch() -> Result<(String, String)> {
                 key: 'Y',
                 desc: "Yea, use this".to_string(),
                 action: Box::new(|| {
-                    let branch =
-                        git::get_branches(remotes.first().cloned()).and_then(choose_branch)?;
+                    let branch = choose_branch(git, Some(&remotes[0]))?;
                     Ok((remotes[0].clone(), branch))
                 }),
             }],
@@ -433,7 +1172,7 @@ fn select_remote_branch() -> Result<(String, String)> {
         .execute()
     } else {
         choose_remote(remotes).and_then(|remote| {
-            let branch = git::get_branches(Some(remote.clone())).and_then(choose_branch)?;
+            let branch = choose_branch(git, Some(&remote))?;
             Ok((remote, branch))
         })
     }
@@ -446,199 +1185,386 @@ fn input_remote() -> Result<(String, String)> {
     })
 }
 
-fn add_remote() -> Result<String> {
+fn add_remote(git: &dyn GitBackend) -> Result<String> {
     input_remote().and_then(|(name, url)| {
-        git::add_remote(&name, &url)?;
+        git.add_remote(&name, &url)?;
         Ok(name)
     })
 }
 
-fn partially_staged() -> Result<()> {
-    Options {
-        prompt: "Files are partially staged, you can choose:",
-        options: vec![
+fn partially_staged<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Menu<'a> {
+    Menu::new(
+        "Files are partially staged, you can choose:",
+        vec![
             OptionItem {
                 key: 'A',
                 desc: "Add files.".to_string(),
-                action: Box::new(add),
+                action: Box::new(move || add(git)),
+            },
+            OptionItem {
+                key: 'S',
+                desc: "Stage some files.".to_string(),
+                action: Box::new(move || stage_files(git, snapshot)),
+            },
+            OptionItem {
+                key: 'P',
+                desc: "Review and stage hunks.".to_string(),
+                action: Box::new(move || stage_hunks(git, snapshot)),
+            },
+            OptionItem {
+                key: 'U',
+                desc: "Unstage some files.".to_string(),
+                action: Box::new(move || unstage_files(git, snapshot)),
+            },
+            OptionItem {
+                key: 'D',
+                desc: "Discard unstaged changes to some files.".to_string(),
+                action: Box::new(move || discard_files(git, snapshot, false)),
             },
             OptionItem {
                 key: 'C',
                 desc: "Commit files".to_string(),
-                action: Box::new(commit),
+                action: Box::new(move || commit(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn fully_staged() -> Result<()> {
-    Options {
-        prompt: "Files are fully staged, you can choose:",
-        options: vec![
+fn fully_staged<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Menu<'a> {
+    Menu::new(
+        "Files are fully staged, you can choose:",
+        vec![
             OptionItem {
                 key: 'C',
                 desc: "Commit files".to_string(),
-                action: Box::new(commit),
+                action: Box::new(move || commit(git)),
+            },
+            OptionItem {
+                key: 'U',
+                desc: "Unstage some files.".to_string(),
+                action: Box::new(move || unstage_files(git, snapshot)),
+            },
+            OptionItem {
+                key: 'D',
+                desc: "Discard changes to some files.".to_string(),
+                action: Box::new(move || discard_files(git, snapshot, true)),
             },
             OptionItem {
                 key: 'O',
                 desc: "Checkout".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
             OptionItem {
                 key: 'M',
                 desc: "Merge".to_string(),
-                action: Box::new(merge),
+                action: Box::new(move || merge(git)),
             },
             OptionItem {
                 key: 'B',
                 desc: "Create a branch.".to_string(),
-                action: Box::new(create_branch),
+                action: Box::new(move || create_branch(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn partially_committed() -> Result<()> {
-    Options {
-        prompt: "Files are partially committed, you can choose:",
-        options: vec![
-            OptionItem {
-                key: 'A',
-                desc: "Add files.".to_string(),
-                action: Box::new(add),
-            },
-            OptionItem {
-                key: 'O',
-                desc: "Checkout".to_string(),
-                action: Box::new(checkout_branch),
-            },
-            OptionItem {
-                key: 'P',
-                desc: "Push to remote".to_string(),
-                action: Box::new(push),
-            },
-        ],
+fn partially_committed<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Menu<'a> {
+    let mut options = vec![
+        OptionItem {
+            key: 'A',
+            desc: "Add files.".to_string(),
+            action: Box::new(move || add(git)),
+        },
+        OptionItem {
+            key: 'O',
+            desc: "Checkout".to_string(),
+            action: Box::new(move || checkout_branch(git)),
+        },
+    ];
+    // the remote commits have to be pulled before anything can be pushed
+    if snapshot.behind > 0 {
+        return Menu::new(
+            "Files are partially committed and the remote branch has new commits, commit the rest to pull them:",
+            options,
+        );
     }
-    .execute()
+    options.push(OptionItem {
+        key: 'P',
+        desc: "Push to remote".to_string(),
+        action: Box::new(move || push(git, snapshot)),
+    });
+    Menu::new("Files are partially committed, you can choose:", options)
 }
 
-fn mess_partially_committed() -> Result<()> {
-    Options {
-        prompt: "Files are partially committed, you can choose:",
-        options: vec![
+fn mess_partially_committed(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "Files are partially committed, you can choose:",
+        vec![
             OptionItem {
                 key: 'C',
                 desc: "Commit files.".to_string(),
-                action: Box::new(commit),
+                action: Box::new(move || commit(git)),
             },
             OptionItem {
                 key: 'O',
                 desc: "Checkout".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn mess_fully_committed() -> Result<()> {
-    Options {
-        prompt: "Files are partially committed and paritially added, you can choose:",
-        options: vec![
+fn mess_fully_committed(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "Files are partially committed and paritially added, you can choose:",
+        vec![
             OptionItem {
                 key: 'C',
                 desc: "Commit files.".to_string(),
-                action: Box::new(commit),
+                action: Box::new(move || commit(git)),
             },
             OptionItem {
                 key: 'O',
                 desc: "Checkout".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
         ],
-    }
-    .execute()
+    )
 }
 
-fn fully_committed() -> Result<()> {
-    Options {
-        prompt: "Files are all committed, you can chose:",
-        options: vec![
+fn fully_committed<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Menu<'a> {
+    Menu::new(
+        "Files are all committed, you can chose:",
+        vec![
             OptionItem {
                 key: 'M',
                 desc: "Merge.".to_string(),
-                action: Box::new(merge),
+                action: Box::new(move || merge(git)),
             },
             OptionItem {
                 key: 'L',
                 desc: "Pull.".to_string(),
-                action: Box::new(pull),
+                action: Box::new(move || pull(git, snapshot)),
             },
             OptionItem {
                 key: 'O',
                 desc: "Checkout".to_string(),
-                action: Box::new(checkout_branch),
+                action: Box::new(move || checkout_branch(git)),
             },
             OptionItem {
                 key: 'P',
                 desc: "Push to remote".to_string(),
-                action: Box::new(push),
+                action: Box::new(move || push(git, snapshot)),
+            },
+        ],
+    )
+    .with_default('P')
+}
+
+fn up_to_date<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Result<Menu<'a>> {
+    let upstream = snapshot.upstream_branch().context("No upstream tracked")?;
+    Ok(Menu::new(
+        format!(
+            "Up to date with {}/{}. Maybe you wanna:",
+            upstream.remote, upstream.branch
+        ),
+        vec![
+            OptionItem {
+                key: 'F',
+                desc: "Fetch to check for new commits.".to_string(),
+                action: Box::new(move || git.fetch(&upstream.remote)),
+            },
+            OptionItem {
+                key: 'M',
+                desc: "Merge another branch".to_string(),
+                action: Box::new(move || merge(git)),
+            },
+            OptionItem {
+                key: 'C',
+                desc: "Checkout a branch.".to_string(),
+                action: Box::new(move || checkout_branch(git)),
+            },
+            OptionItem {
+                key: 'B',
+                desc: "Create a branch.".to_string(),
+                action: Box::new(move || create_branch(git)),
+            },
+        ],
+    ))
+}
+
+/// Lists the commits of the upstream that HEAD doesn't contain yet.
+fn incoming_commits(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<String> {
+    let upstream = snapshot.upstream_branch().context("No upstream tracked")?;
+    let commits = git.incoming_commits(&format!("{}/{}", upstream.remote, upstream.branch))?;
+    Ok(format!(
+        "{} incoming commit(s) from {}/{}:\n{}",
+        snapshot.behind, upstream.remote, upstream.branch, commits
+    ))
+}
+
+fn behind<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Result<Menu<'a>> {
+    let upstream = snapshot.upstream_branch().context("No upstream tracked")?;
+    let remote_branch = format!("{}/{}", upstream.remote, upstream.branch);
+    Ok(Menu::new(
+        "The remote branch has new commits, you can choose:",
+        vec![
+            OptionItem {
+                key: 'R',
+                desc: "Pull with rebase.".to_string(),
+                action: Box::new(move || git.pull_rebase(&upstream.remote, &upstream.branch)),
+            },
+            OptionItem {
+                key: 'M',
+                desc: "Merge the remote branch.".to_string(),
+                action: Box::new(move || git.merge(&remote_branch)),
+            },
+            OptionItem {
+                key: 'O',
+                desc: "Checkout".to_string(),
+                action: Box::new(move || checkout_branch(git)),
             },
         ],
+    )
+    .with_notice(incoming_commits(git, snapshot)?))
+}
+
+fn diverged<'a>(git: &'a dyn GitBackend, snapshot: &'a RepoSnapshot) -> Result<Menu<'a>> {
+    let upstream = snapshot.upstream_branch().context("No upstream tracked")?;
+    let remote_branch = format!("{}/{}", upstream.remote, upstream.branch);
+    let (remote, branch) = (upstream.remote.clone(), upstream.branch.clone());
+    let protected = config::current().is_protected(&branch);
+    let mut options = vec![
+        OptionItem {
+            key: 'R',
+            desc: "Pull with rebase, replay local commits on top of the remote ones.".to_string(),
+            action: Box::new(move || git.pull_rebase(&upstream.remote, &upstream.branch)),
+        },
+        OptionItem {
+            key: 'M',
+            desc: "Merge the remote branch.".to_string(),
+            action: Box::new(move || git.merge(&remote_branch)),
+        },
+    ];
+    // protected branches keep their remote commits
+    if !protected {
+        options.push(OptionItem {
+            key: 'F',
+            desc: "Force push with lease, drop the remote commits.".to_string(),
+            action: Box::new(move || {
+                output_notice("\nPushing, please wait a moment...\n")?;
+                git.push_force_with_lease(&remote, &branch)
+            }),
+        });
     }
-    .execute()
+    Ok(Menu::new(
+        format!(
+            "Local and remote branch have diverged, {} commit(s) to push and {} to pull, you can choose:",
+            snapshot.ahead, snapshot.behind
+        ),
+        options,
+    )
+    .with_notice(incoming_commits(git, snapshot)?))
 }
 
-fn push() -> Result<()> {
-    let upstream = git::get_upstream()?;
-    let (remote, branch) = if let Some(upstream) = upstream {
-        let branch_info: Vec<_> = upstream.splitn(2, '/').collect();
+fn push(git: &dyn GitBackend, snapshot: &RepoSnapshot) -> Result<()> {
+    let (remote, branch) = if let Some(upstream) = snapshot.upstream_branch() {
         Options {
             prompt: &format!(
-                "There is an upstream: {}, do you wanna push to it?",
-                upstream
+                "There is an upstream: {}/{}, do you wanna push to it?",
+                upstream.remote, upstream.branch
             ),
             options: vec![OptionItem {
                 key: 'Y',
                 desc: "Yes, push to upstream branch".to_string(),
-                action: Box::new(move || {
-                    Ok((branch_info[0].to_string(), branch_info[1].to_string()))
-                }),
+                action: Box::new(move || Ok((upstream.remote.clone(), upstream.branch.clone()))),
             }],
         }
         .execute()
     } else {
-        select_remote_branch()
+        select_remote_branch(git)
     }?;
     output_notice("\nPushing, please wait a moment...\n")?;
-    git::push(Some(GitRemoteBranch { remote, branch }))
+    git.push(&remote, &branch)
 }
 
-fn conflicted() -> Result<()> {
-    Options {
-        prompt: "Files are identified conflicted, confirm you have resolved:",
-        options: vec![
+fn conflicted(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "Files are identified conflicted, confirm you have resolved:",
+        vec![
             OptionItem {
                 key: 'Y',
                 desc: "Yes, I have resolved.".to_string(),
-                action: Box::new(git::add_all),
+                action: Box::new(move || git.add_all()),
             },
             OptionItem {
                 key: 'N',
                 desc: "No, I haven't resolved.".to_string(),
-                action: Box::new(|| {
+                action: Box::new(move || {
                     output_error("Please resolve the conflict first.")?;
-                    process::exit(1);
+                    bail!(FlowOutcome::ConflictsPending)
                 }),
             },
         ],
+    )
+}
+
+fn in_progress(git: &dyn GitBackend, operation: GitOperation) -> Menu<'_> {
+    let command = operation.command();
+    let mut options = vec![
+        OptionItem {
+            key: 'Y',
+            desc: "I have resolved the conflicts, add all files.".to_string(),
+            action: Box::new(move || git.add_all()),
+        },
+        OptionItem {
+            key: 'C',
+            desc: format!("Continue the {}.", operation),
+            action: Box::new(move || git.operation(command, "continue")),
+        },
+    ];
+    if operation.can_skip() {
+        options.push(OptionItem {
+            key: 'S',
+            desc: "Skip the current commit.".to_string(),
+            action: Box::new(move || git.operation(command, "skip")),
+        });
     }
-    .execute()
+    options.push(OptionItem {
+        key: 'A',
+        desc: format!("Abort the {}.", operation),
+        action: Box::new(move || git.operation(command, "abort")),
+    });
+    Menu::new(
+        format!("A {} is in progress, you can choose:", operation),
+        options,
+    )
 }
 
-fn index_to_char(n: usize) -> char {
-    std::char::from_u32((n + 1) as u32 + '0' as u32).unwrap()
+fn bisecting(git: &dyn GitBackend) -> Menu<'_> {
+    Menu::new(
+        "A bisect is in progress, mark the current commit:",
+        vec![
+            OptionItem {
+                key: 'G',
+                desc: "Good.".to_string(),
+                action: Box::new(move || git.bisect("good")),
+            },
+            OptionItem {
+                key: 'B',
+                desc: "Bad.".to_string(),
+                action: Box::new(move || git.bisect("bad")),
+            },
+            OptionItem {
+                key: 'S',
+                desc: "Skip, it can't be tested.".to_string(),
+                action: Box::new(move || git.bisect("skip")),
+            },
+            OptionItem {
+                key: 'R',
+                desc: "Reset, finish bisecting.".to_string(),
+                action: Box::new(move || git.bisect("reset")),
+            },
+        ],
+    )
 }
@@ -1,160 +1,546 @@
 use anyhow::{bail, Context, Result};
-use std::process::Command;
+use std::{
+    fmt, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    time::Instant,
+};
 
-use crate::{output::command_output, status::GitRemoteBranch};
+use crate::{
+    output::{
+        command_output,
+        CommandKind::{self, Change, Query},
+    },
+    status::{BranchInfo, GitRemoteBranch},
+};
+
+/// A git command that ran but failed.
+#[derive(Debug)]
+pub struct GitError(String);
+
+impl GitError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for GitError {}
+
+/// Runs `git args`, shown as the verbosity allows for its `kind`.
+fn run(kind: CommandKind, args: &[&str]) -> Result<Output> {
+    run_command(kind, Command::new("git").args(args))
+}
+
+fn run_command(kind: CommandKind, command: &mut Command) -> Result<Output> {
+    run_with_input(kind, command, None)
+}
+
+/// Like [`run_command`], writing `input` to the command's stdin.
+fn run_with_input(kind: CommandKind, command: &mut Command, input: Option<&str>) -> Result<Output> {
+    let line = format!(
+        "git {}",
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    let start = Instant::now();
+    let output = match input {
+        None => command.output(),
+        Some(input) => command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                // dropping stdin closes it, so the command sees the end of the input
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(input.as_bytes())?;
+                child.wait_with_output()
+            }),
+    }
+    .context(format!("Failed to execute {}", line))?;
+    command_output(&line, kind, start.elapsed(), output.clone())?;
+    Ok(output)
+}
 
 pub fn init() -> Result<()> {
-    let output = Command::new("git")
-        .arg("init")
-        .output()
-        .context("git init failed")?;
-    command_output(Some("git init"), output)?;
+    run(Change, &["init"])?;
     Ok(())
 }
 
 pub fn add_all() -> Result<()> {
-    let output = Command::new("git")
-        .arg("add")
-        .arg("--all")
-        .output()
-        .context("git add failed")?;
-    command_output(Some("git add --all"), output)?;
+    run(Change, &["add", "--all"])?;
     Ok(())
 }
 
-pub fn has_file_added() -> Result<bool> {
-    let output = Command::new("git")
-        .arg("diff")
-        .arg("--cached")
-        .output()
-        .context("git diff failed")?;
+pub fn stage(paths: &[String]) -> Result<()> {
+    let mut args = vec!["add", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let output = run(Change, &args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to stage the files: {}",
+            stderr
+        )));
+    }
+    Ok(())
+}
 
-    command_output(Some("git diff --cached"), output.clone())?;
-    Ok(!output.stdout.is_empty())
+/// Takes `paths` out of the index, which also works before the first commit.
+pub fn unstage(paths: &[String]) -> Result<()> {
+    let mut args = vec!["reset", "-q", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let output = run(Change, &args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to unstage the files: {}",
+            stderr
+        )));
+    }
+    Ok(())
 }
 
-pub fn any_changes() -> Result<bool> {
-    let output = Command::new("git")
-        .arg("status")
-        .arg("--porcelain")
-        .output()
-        .context("git status failed")?;
-    command_output(Some("git status --porcelain"), output.clone())?;
-    Ok(!output.stdout.is_empty())
+/// The unstaged changes of `path` as a unified diff, empty when there are none.
+pub fn diff_file(path: &str) -> Result<String> {
+    diff(&[], path)
 }
 
-pub fn git_status_short() -> Result<String> {
-    let output = Command::new("git")
-        .arg("status")
-        .arg("-s")
-        .output()
-        .context("git status failed")?;
-    command_output(Some("git status -s"), output.clone())?;
+/// The staged changes of `path` as a unified diff, empty when there are none.
+pub fn diff_staged(path: &str) -> Result<String> {
+    diff(&["--cached"], path)
+}
+
+fn diff(options: &[&str], path: &str) -> Result<String> {
+    let mut args = vec![
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+    ];
+    args.extend(options);
+    args.extend(["--", path]);
+    let output = run(Query, &args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to diff {}: {}",
+            path, stderr
+        )));
+    }
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-pub fn check_in_git_repo() -> Result<bool> {
-    let output = Command::new("git")
-        .arg("rev-parse")
-        .arg("--is-inside-work-tree")
-        .output()
-        .context("Failed to check in git repo")?;
-    command_output(Some("git rev-parse --is-inside-work-tree"), output.clone())?;
-    Ok(output.stdout == b"true\n")
+/// The content of the untracked file `path` as a diff adding it.
+pub fn diff_untracked(path: &str) -> Result<String> {
+    let output = run(
+        Query,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-index",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            "--",
+            "/dev/null",
+            path,
+        ],
+    )?;
+    // --no-index exits with 1 when the files differ
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to diff {}: {}",
+            path, stderr
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-pub fn get_upstream() -> Result<Option<String>> {
-    let output = Command::new("git")
-        .arg("rev-parse")
-        .arg("--abbrev-ref")
-        .arg("--symbolic-full-name")
-        .arg("@{upstream}")
-        .output();
-    match output {
-        Ok(output) => {
-            command_output(
-                Some("git rev-parse --abbrev-ref --symbolic-full-name @{upstream}"),
-                output.clone(),
-            )?;
-            if !output.status.success() {
-                Ok(None)
-            } else {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                Ok(Some(stdout.trim().to_string()))
-            }
+/// Where [`backup`] keeps the changes discarded last, earlier ones are in its reflog.
+pub const BACKUP_REF: &str = "refs/gitflow/discarded";
+
+/// Commits the index with the work tree content of `paths`, untracked files included, on top
+/// of HEAD to [`BACKUP_REF`]. A copy of the index in the git dir is used, so nothing else
+/// changes.
+pub fn backup(paths: &[String], message: &str) -> Result<()> {
+    let checked = |output: Output| {
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(GitError::new(format!(
+                "Failed to back up the changes: {}",
+                stderr
+            )));
         }
-        Err(_) => Ok(None),
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let index = checked(run(Query, &["rev-parse", "--git-path", "index"])?)?;
+    let backup_index = git_dir()?.join("gitflow-backup.index");
+    let tree = fs::copy(&index, &backup_index)
+        .with_context(|| format!("Failed to copy {}", index))
+        .and_then(|_| {
+            run_command(
+                Change,
+                Command::new("git")
+                    .env("GIT_INDEX_FILE", &backup_index)
+                    .args(["add", "--"])
+                    .args(paths),
+            )
+        })
+        .and_then(checked)
+        .and_then(|_| {
+            run_command(
+                Change,
+                Command::new("git")
+                    .env("GIT_INDEX_FILE", &backup_index)
+                    .arg("write-tree"),
+            )
+        })
+        .and_then(checked);
+    let _ = fs::remove_file(&backup_index);
+    let commit = checked(run(
+        Change,
+        &["commit-tree", &tree?, "-p", "HEAD", "-m", message],
+    )?)?;
+    checked(run(
+        Change,
+        &[
+            "update-ref",
+            "--create-reflog",
+            "-m",
+            message,
+            BACKUP_REF,
+            &commit,
+        ],
+    )?)?;
+    Ok(())
+}
+
+/// Throws away the unstaged changes of the tracked `paths`, keeping what's staged, or all their
+/// changes since HEAD when `staged`.
+pub fn restore(paths: &[String], staged: bool) -> Result<()> {
+    let mut args = vec!["restore", "--worktree"];
+    if staged {
+        args.extend(["--staged", "--source=HEAD"]);
+    }
+    args.push("--");
+    args.extend(paths.iter().map(String::as_str));
+    let output = run(Change, &args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to discard the changes: {}",
+            stderr
+        )));
     }
+    Ok(())
 }
 
-pub fn set_upstream(remote: &str, branch: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("branch")
-        .arg("--set-upstream-to")
-        .arg(format!("{}/{}", remote, branch))
-        .output()
-        .context("Failed to set upstream")?;
-    command_output(
-        Some(&format!(
-            "git branch --set-upstream-to {}/{}",
-            remote, branch
-        )),
-        output.clone(),
+/// Deletes the untracked `paths`.
+pub fn clean(paths: &[String]) -> Result<()> {
+    let mut args = vec!["clean", "-f", "-q", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let output = run(Change, &args)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to delete the untracked files: {}",
+            stderr
+        )));
+    }
+    Ok(())
+}
+
+/// Stages `patch`, a diff of the work tree against the index.
+pub fn apply_cached(patch: &str) -> Result<()> {
+    let output = run_with_input(
+        Change,
+        Command::new("git").args(["apply", "--cached", "-"]),
+        Some(patch),
     )?;
     if !output.status.success() {
-        bail!("Failed to set upstream");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to stage the hunks: {}",
+            stderr
+        )));
     }
     Ok(())
 }
 
-pub fn get_remote_names() -> Result<Vec<String>> {
-    let output = Command::new("git")
-        .arg("remote")
-        .output()
-        .context("Failed to execute git command")?;
+/// The editor git opens, from `GIT_EDITOR`, `core.editor`, `VISUAL` or `EDITOR`.
+pub fn editor() -> Result<String> {
+    let output = run(Query, &["var", "GIT_EDITOR"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to find an editor: {}",
+            stderr
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A commit to make: its message, which may have a body after a blank line, and the
+/// `git commit` options that change how it's made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Commit {
+    pub message: String,
+    /// Replaces the last commit instead of adding one.
+    pub amend: bool,
+    /// Adds a `Signed-off-by` trailer.
+    pub signoff: bool,
+    /// Skips the pre-commit and commit-msg hooks.
+    pub no_verify: bool,
+}
+
+impl Commit {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Reads a `git commit` command, such as the ones aicommit suggests, without running it.
+    /// Each `-m` is a paragraph of the message, as git takes them.
+    pub fn parse(command: &str) -> Result<Self> {
+        let words = split_words(command)?;
+        let mut words = words.iter().map(String::as_str);
+        if (words.next(), words.next()) != (Some("git"), Some("commit")) {
+            bail!("Not a git commit command: {}", command.trim());
+        }
+        let mut commit = Commit::default();
+        let mut paragraphs = vec![];
+        while let Some(word) = words.next() {
+            match word {
+                "-m" | "--message" => paragraphs.push(
+                    words
+                        .next()
+                        .with_context(|| format!("Missing the message after {}", word))?,
+                ),
+                "--amend" => commit.amend = true,
+                "-s" | "--signoff" => commit.signoff = true,
+                "-n" | "--no-verify" => commit.no_verify = true,
+                _ => match word.strip_prefix("--message=") {
+                    Some(paragraph) => paragraphs.push(paragraph),
+                    None => bail!("Unsupported option of git commit: {}", word),
+                },
+            }
+        }
+        if paragraphs.is_empty() {
+            bail!("No commit message in: {}", command.trim());
+        }
+        commit.message = paragraphs.join("\n\n");
+        Ok(commit)
+    }
+
+    /// The arguments of `git commit`.
+    pub fn args(&self) -> Vec<&str> {
+        let mut args = vec!["commit"];
+        for (enabled, flag) in [
+            (self.amend, "--amend"),
+            (self.signoff, "--signoff"),
+            (self.no_verify, "--no-verify"),
+        ] {
+            if enabled {
+                args.push(flag);
+            }
+        }
+        args.extend(["-m", &self.message]);
+        args
+    }
+}
+
+impl fmt::Display for Commit {
+    /// The command as it would be typed in a shell.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = self.args();
+        let (message, flags) = args.split_last().expect("the message is the last argument");
+        write!(
+            f,
+            "git {} '{}'",
+            flags.join(" "),
+            message.replace('\'', r"'\''")
+        )
+    }
+}
+
+/// Splits `command` into words the way a shell does with quotes and backslashes, expanding
+/// nothing.
+fn split_words(command: &str) -> Result<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated quote in: {}", command.trim()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!("Unterminated quote in: {}", command.trim()),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated quote in: {}", command.trim()),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Makes `commit`, with git run directly so nothing in the message reaches a shell.
+pub fn commit(commit: &Commit) -> Result<()> {
+    let output = run(Change, &commit.args())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to commit: {}", stderr)));
+    }
+    Ok(())
+}
+
+pub fn git_status_porcelain() -> Result<String> {
+    let output = run(Query, &["status", "--porcelain=v2", "--branch", "-z"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to get git status: {}",
+            stderr
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
 
-    command_output(Some("git remote"), output.clone())?;
+pub fn check_in_git_repo() -> Result<bool> {
+    let output = run(Query, &["rev-parse", "--is-inside-work-tree"])?;
+    Ok(output.stdout == b"true\n")
+}
+
+pub fn get_config(key: &str) -> Result<Option<String>> {
+    let output = run(Query, &["config", "--get", key])?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(Some(stdout.trim().to_string()))
+}
+
+pub fn set_config(key: &str, value: &str) -> Result<()> {
+    let output = run(Change, &["config", key, value])?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to get remote names: {}", stderr);
+        bail!(GitError::new(format!("Failed to set {}: {}", key, stderr)));
+    }
+    Ok(())
+}
+
+pub fn git_dir() -> Result<PathBuf> {
+    let output = run(Query, &["rev-parse", "--git-dir"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to get git dir: {}", stderr)));
     }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.split_whitespace().map(String::from).collect())
+    Ok(PathBuf::from(stdout.trim()))
 }
 
-pub fn get_current_branch() -> Result<String> {
-    let output = Command::new("git")
-        .arg("rev-parse")
-        .arg("--abbrev-ref")
-        .arg("HEAD")
-        .output()
-        .context("Failed to execute git command")?;
-    command_output(Some("git rev-parse --abbrev-ref HEAD"), output.clone())?;
+pub fn work_tree() -> Result<PathBuf> {
+    let output = run(Query, &["rev-parse", "--show-toplevel"])?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to get current branch: {}", stderr);
+        bail!(GitError::new(format!(
+            "Failed to get work tree: {}",
+            stderr
+        )));
     }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.trim().to_string())
+    Ok(PathBuf::from(stdout.trim()))
+}
+
+pub fn set_upstream(remote: &str, branch: &str) -> Result<()> {
+    let output = run(
+        Change,
+        &[
+            "branch",
+            "--set-upstream-to",
+            &format!("{}/{}", remote, branch),
+        ],
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to set upstream: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+pub fn get_remote_names() -> Result<Vec<String>> {
+    let output = run(Query, &["remote"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to get remote names: {}",
+            stderr
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.split_whitespace().map(String::from).collect())
 }
 
 pub fn get_branches(remote_name: Option<String>) -> Result<Vec<String>> {
     match remote_name {
         Some(remote_name) => {
-            let output = Command::new("git")
-                .arg("branch")
-                .arg("-r")
-                .arg("--list")
-                .arg("--format=%(refname:short)")
-                .output()
-                .context("Failed to execute git command")?;
-            command_output(
-                Some("git branch -r --list --format=%(refname:short)"),
-                output.clone(),
+            let output = run(
+                Query,
+                &["branch", "-r", "--list", "--format=%(refname:short)"],
             )?;
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                bail!("Failed to get branches: {}", stderr);
+                bail!(GitError::new(format!("Failed to get branches: {}", stderr)));
             }
             let branches = String::from_utf8_lossy(&output.stdout)
                 .lines()
@@ -165,19 +551,10 @@ pub fn get_branches(remote_name: Option<String>) -> Result<Vec<String>> {
             Ok(branches)
         }
         None => {
-            let output = Command::new("git")
-                .arg("branch")
-                .arg("--list")
-                .arg("--format=%(refname:short)")
-                .output()
-                .context("Failed to execute git command")?;
-            command_output(
-                Some("git branch --list --format=%(refname:short)"),
-                output.clone(),
-            )?;
+            let output = run(Query, &["branch", "--list", "--format=%(refname:short)"])?;
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                bail!("Failed to get branches: {}", stderr);
+                bail!(GitError::new(format!("Failed to get branches: {}", stderr)));
             }
             let branches = String::from_utf8_lossy(&output.stdout)
                 .lines()
@@ -189,183 +566,255 @@ pub fn get_branches(remote_name: Option<String>) -> Result<Vec<String>> {
     }
 }
 
-pub fn fetch(remote: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("fetch")
-        .arg(remote)
-        .output()
-        .context("Failed to execute git fetch")?;
+/// Lists local branches, or the branches of `remote`, most recently committed first.
+pub fn branch_details(remote: Option<&str>) -> Result<Vec<BranchInfo>> {
+    let prefix = match remote {
+        Some(remote) => format!("refs/remotes/{}/", remote),
+        None => "refs/heads/".to_string(),
+    };
+    let format = "--format=%(symref)%00%(refname)%00%(objectname:short)%00%(committerdate:unix)%00%(subject)";
+    let output = run(
+        Query,
+        &["for-each-ref", "--sort=-committerdate", format, &prefix],
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to get branches: {}", stderr)));
+    }
+    let mut branches = vec![];
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<_> = line.splitn(5, '\0').collect();
+        let [symref, refname, commit, committed_at, subject] = fields[..] else {
+            bail!(GitError::new(format!(
+                "Failed to parse git for-each-ref output: {}",
+                line
+            )));
+        };
+        // e.g. origin/HEAD
+        if !symref.is_empty() {
+            continue;
+        }
+        let Some(name) = refname.strip_prefix(&prefix) else {
+            continue;
+        };
+        branches.push(BranchInfo {
+            name: name.to_string(),
+            commit: commit.to_string(),
+            subject: subject.to_string(),
+            committed_at: committed_at.parse().unwrap_or_default(),
+        });
+    }
+    Ok(branches)
+}
 
-    command_output(Some(&format!("git fetch {}", remote)), output.clone())?;
+pub fn fetch(remote: &str) -> Result<()> {
+    let output = run(Change, &["fetch", remote])?;
     if !output.status.success() {
-        bail!("Failed to checkout branch");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to fetch {}: {}",
+            remote,
+            stderr.trim()
+        )));
     }
     Ok(())
 }
 
 pub fn merge(branch: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("merge")
-        .arg("--no-edit")
-        .arg(branch)
-        .output()
-        .context("Failed to execute git merge")?;
-    command_output(
-        Some(&format!("git merge --no-edit {}", branch)),
-        output.clone(),
-    )?;
+    let output = run(Change, &["merge", "--no-edit", branch])?;
     if !output.status.success() {
-        bail!("Failed to merge branch");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to merge branch: {}",
+            stderr.trim()
+        )));
     }
     Ok(())
 }
 
 pub fn pull(branch_info: Option<(&str, &str)>) -> Result<()> {
     let output = match branch_info {
-        Some((remote, branch)) => Command::new("git")
-            .arg("pull")
-            .arg(remote)
-            .arg(branch)
-            .output(),
-        None => Command::new("git").arg("pull").output(),
-    }
-    .context("Failed to execute git pull")?;
-    command_output(
-        Some(&format!(
-            "git pull {}",
-            if let Some((remote, branch)) = branch_info {
-                format!("{} {}", remote, branch)
-            } else {
-                "".to_string()
-            }
-        )),
-        output.clone(),
-    )?;
+        Some((remote, branch)) => run(Change, &["pull", remote, branch]),
+        None => run(Change, &["pull"]),
+    }?;
     if !output.status.success() {
-        bail!("Failed to pull");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to pull: {}", stderr.trim())));
+    }
+    Ok(())
+}
+
+pub fn pull_rebase(remote: &str, branch: &str) -> Result<()> {
+    let output = run(Change, &["pull", "--rebase", remote, branch])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to pull: {}", stderr.trim())));
     }
     Ok(())
 }
 
 pub fn push(remote: Option<GitRemoteBranch>) -> Result<()> {
-    let output = if let Some(remote) = remote.clone() {
+    let output = match remote {
+        Some(remote) => run(Change, &["push", &remote.remote, &remote.branch]),
+        None => run(Change, &["push"]),
+    }?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to push: {}", stderr.trim())));
+    }
+    Ok(())
+}
+
+pub fn push_force_with_lease(remote: &str, branch: &str) -> Result<()> {
+    let output = run(Change, &["push", "--force-with-lease", remote, branch])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to push: {}", stderr.trim())));
+    }
+    Ok(())
+}
+
+pub fn push_set_upstream(remote: &str, branch: &str) -> Result<()> {
+    let output = run(Change, &["push", "--set-upstream", remote, branch])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!("Failed to push: {}", stderr.trim())));
+    }
+    Ok(())
+}
+
+/// Drives a paused merge, rebase, cherry-pick, revert or am, e.g. `git rebase --continue`.
+pub fn operation(command: &str, action: &str) -> Result<()> {
+    let flag = format!("--{}", action);
+    // keep the prepared commit message instead of opening an editor
+    let output = run_command(
+        Change,
         Command::new("git")
-            .arg("push")
-            .arg(remote.remote)
-            .arg(remote.branch)
-            .output()
-    } else {
-        Command::new("git").arg("push").output()
-    }
-    .context("Failed to execute git push")?;
-    command_output(
-        Some(&format!(
-            "git push {}",
-            if let Some(remote) = remote {
-                format!("{} {}", remote.remote, remote.branch)
-            } else {
-                "".to_string()
-            }
-        )),
-        output.clone(),
+            .arg(command)
+            .arg(&flag)
+            .env("GIT_EDITOR", "true"),
     )?;
     if !output.status.success() {
-        bail!("Failed to push");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to {} {}: {}",
+            action,
+            command,
+            stderr.trim()
+        )));
     }
     Ok(())
 }
 
-pub fn has_uncommitted_changes() -> Result<bool> {
-    let output = Command::new("git")
-        .arg("diff")
-        .arg("--cached")
-        .arg("--exit-code")
-        .output() // can't use status() directly as it will output the git repsponse
-        .context("Failed to execute git command")?;
-    command_output(Some("git diff --cached --exit-code"), output.clone())?;
-
-    Ok(!output.status.success())
+pub fn bisect(subcommand: &str) -> Result<()> {
+    let output = run(Change, &["bisect", subcommand])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to bisect {}: {}",
+            subcommand,
+            stderr.trim()
+        )));
+    }
+    Ok(())
 }
 
 pub fn checkout(branch: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("checkout")
-        .arg(branch)
-        .output() // can't use status() directly as it will output the git repsponse
-        .context("Failed to execute git command")?;
-
-    command_output(Some(&format!("git checkout {}", branch)), output.clone())?;
+    let output = run(Change, &["checkout", branch])?;
     if !output.status.success() {
-        bail!("Failed to checkout branch");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to checkout branch: {}",
+            stderr.trim()
+        )));
     }
     Ok(())
 }
 
 pub fn create_checkout(branch: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("checkout")
-        .arg("-b")
-        .arg(branch)
-        .output() // can't use status() directly as it will output the git repsponse
-        .context("Failed to execute git command")?;
-
-    command_output(Some(&format!("git checkout -b {}", branch)), output.clone())?;
+    let output = run(Change, &["checkout", "-b", branch])?;
     if !output.status.success() {
-        bail!("Failed to checkout branch");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to checkout branch: {}",
+            stderr.trim()
+        )));
     }
     Ok(())
 }
 
 pub fn add_remote(name: &str, url: &str) -> Result<()> {
-    let output = Command::new("git")
-        .arg("remote")
-        .arg("add")
-        .arg(name)
-        .arg(url)
-        .output()
-        .context("Failed to execute git push")?;
-    command_output(
-        Some(&format!("git remote add {} {}", name, url)),
-        output.clone(),
-    )?;
+    let output = run(Change, &["remote", "add", name, url])?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to add remote: {}", stderr);
+        bail!(GitError::new(format!("Failed to add remote: {}", stderr)));
     }
     Ok(())
 }
 
-pub fn has_commit_to_push(remote: String, branch: String) -> Result<bool> {
-    // The `git log --oneline origin/master..master` command shows commits that are in the local branch but not in the remote branch. If the output is empty, there are no commits to push.
-    let arg = &format!("{}/{}..{}", remote, branch, branch);
-    let output = Command::new("git")
-        .arg("log")
-        .arg("--oneline")
-        .arg(arg)
-        .output()
-        .context("Failed to execute git log --online")?;
-    command_output(Some(&format!("git log --oneline {}", arg)), output.clone())?;
+/// Counts the commits HEAD is ahead of and behind `base`.
+pub fn ahead_behind(base: &str) -> Result<(u32, u32)> {
+    let arg = &format!("HEAD...{}", base);
+    let output = run(Query, &["rev-list", "--left-right", "--count", arg])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to execute git rev-list: {}",
+            stderr
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let counts: Vec<u32> = stdout
+        .split_whitespace()
+        .map(|count| count.parse())
+        .collect::<Result<_, _>>()
+        .context("Failed to parse git rev-list output")?;
+    match counts[..] {
+        [ahead, behind] => Ok((ahead, behind)),
+        _ => bail!(GitError::new(format!(
+            "Unexpected git rev-list output: {}",
+            stdout
+        ))),
+    }
+}
+
+/// Lists the commits of `upstream` that HEAD doesn't contain yet.
+pub fn incoming_commits(upstream: &str) -> Result<String> {
+    let arg = &format!("HEAD..{}", upstream);
+    let output = run(Query, &["log", "--oneline", arg])?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to execute git log --oneline: {}", stderr);
+        bail!(GitError::new(format!(
+            "Failed to execute git log --oneline: {}",
+            stderr
+        )));
     }
-    Ok(!output.stdout.is_empty())
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
 pub fn diff_remote_stat(remote: String, branch: String) -> Result<String> {
-    let output = Command::new("git")
-        .arg("diff")
-        .arg("--stat")
-        .arg(format!("{}/{}", remote, branch))
-        .output()
-        .context("Failed to execute git diff")?;
-    command_output(
-        Some(&format!("git diff --stat {}/{}", remote, branch)),
-        output.clone(),
+    let output = run(
+        Query,
+        &["diff", "--stat", &format!("{}/{}", remote, branch)],
     )?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("Failed to execute git diff: {}", stderr);
+        bail!(GitError::new(format!(
+            "Failed to execute git diff: {}",
+            stderr
+        )));
     }
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
+
+pub fn stash_count() -> Result<usize> {
+    let output = run(Query, &["stash", "list"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(GitError::new(format!(
+            "Failed to execute git stash list: {}",
+            stderr
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().count())
+}
@@ -0,0 +1,311 @@
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+
+use crate::output::{colorful_print, theme, Styles};
+
+/// The diff of one file, as `git diff -- <path>` prints it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileDiff {
+    /// The lines before the first hunk, from `diff --git` to `+++`.
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+/// A run of changed lines with the context around them. Lines keep their ` `, `+`, `-` or
+/// `\` prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    /// The first line of the hunk in the old file, counting from 1.
+    pub old_start: u32,
+    pub old_len: u32,
+    pub new_start: u32,
+    pub new_len: u32,
+    /// What follows the second `@@`, usually the enclosing function.
+    pub section: String,
+    pub lines: Vec<String>,
+}
+
+impl FileDiff {
+    /// Parses the diff of a single file, `None` when it has no hunks, e.g. for a binary file
+    /// or a mode change.
+    pub fn parse(diff: &str) -> Result<Option<Self>> {
+        let mut header = vec![];
+        let mut hunks: Vec<Hunk> = vec![];
+        for line in diff.lines() {
+            if line.starts_with("@@") {
+                hunks.push(Hunk::parse_header(line)?);
+            } else if let Some(hunk) = hunks.last_mut() {
+                hunk.lines.push(line.to_string());
+            } else if line.starts_with("diff --git") && !header.is_empty() {
+                bail!("Expected the diff of a single file");
+            } else {
+                header.push(line.to_string());
+            }
+        }
+        Ok((!hunks.is_empty()).then_some(Self { header, hunks }))
+    }
+
+    /// A patch applying only `hunks` to the old file. They're taken in order of their old
+    /// start, and the new starts are recomputed, as the hunks left out change nothing. Hunks
+    /// sharing context, as the parts of a split hunk do, are joined into one.
+    pub fn patch(&self, hunks: &[Hunk]) -> String {
+        let mut sorted: Vec<_> = hunks.iter().collect();
+        sorted.sort_by_key(|hunk| hunk.old_start);
+        let mut hunks: Vec<Hunk> = vec![];
+        for hunk in sorted {
+            if let Some(joined) = hunks.last().and_then(|last| last.joined(hunk)) {
+                hunks.pop();
+                hunks.push(joined);
+            } else {
+                hunks.push(hunk.clone());
+            }
+        }
+        let mut patch: String = self
+            .header
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let mut offset = 0i64;
+        for hunk in hunks {
+            let hunk = Hunk {
+                new_start: (i64::from(hunk.old_start) + offset) as u32,
+                ..hunk
+            };
+            offset += i64::from(hunk.new_len) - i64::from(hunk.old_len);
+            patch.push_str(&hunk.to_string());
+        }
+        patch
+    }
+}
+
+impl Hunk {
+    /// Parses `@@ -old_start,old_len +new_start,new_len @@ section`.
+    fn parse_header(line: &str) -> Result<Self> {
+        let invalid = || format!("Invalid hunk header: {}", line);
+        let rest = line.strip_prefix("@@ -").with_context(invalid)?;
+        let (ranges, section) = rest.split_once(" @@").with_context(invalid)?;
+        let (old, new) = ranges.split_once(" +").with_context(invalid)?;
+        let (old_start, old_len) = parse_range(old).with_context(invalid)?;
+        let (new_start, new_len) = parse_range(new).with_context(invalid)?;
+        Ok(Self {
+            old_start,
+            old_len,
+            new_start,
+            new_len,
+            section: section.trim_start().to_string(),
+            lines: vec![],
+        })
+    }
+
+    /// Whether [`Hunk::split`] would make more than one hunk of it.
+    pub fn can_split(&self) -> bool {
+        self.change_runs().len() > 1
+    }
+
+    /// Splits the hunk around the context between its runs of changes, as `git add -p` does.
+    /// The context in between belongs to the runs on both sides of it.
+    pub fn split(&self) -> Vec<Hunk> {
+        let runs = self.change_runs();
+        if runs.len() < 2 {
+            return vec![self.clone()];
+        }
+        // the line numbers each line starts at
+        let mut starts = vec![];
+        let (mut old, mut new) = (self.old_start, self.new_start);
+        for line in &self.lines {
+            starts.push((old, new));
+            match line.chars().next() {
+                Some('-') => old += 1,
+                Some('+') => new += 1,
+                Some('\\') => {}
+                _ => {
+                    old += 1;
+                    new += 1;
+                }
+            }
+        }
+        (0..runs.len())
+            .map(|idx| {
+                let from = if idx == 0 { 0 } else { runs[idx - 1].1 };
+                let to = runs.get(idx + 1).map_or(self.lines.len(), |run| run.0);
+                let (old_start, new_start) = starts[from];
+                Hunk {
+                    old_start,
+                    new_start,
+                    section: self.section.clone(),
+                    ..Hunk::counted(self.lines[from..to].to_vec())
+                }
+            })
+            .collect()
+    }
+
+    /// The ranges of lines that are changes, each followed by context or the end.
+    fn change_runs(&self) -> Vec<(usize, usize)> {
+        let mut runs: Vec<(usize, usize)> = vec![];
+        for (idx, line) in self.lines.iter().enumerate() {
+            let change = match line.chars().next() {
+                Some('+') | Some('-') => true,
+                // "\ No newline at end of file" belongs to the line before it
+                Some('\\') => runs.last().is_some_and(|run| run.1 == idx),
+                _ => false,
+            };
+            match runs.last_mut() {
+                Some(run) if change && run.1 == idx => run.1 = idx + 1,
+                _ if change => runs.push((idx, idx + 1)),
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    /// A hunk of `lines` at the same place, e.g. after the user edited it. Lines starting with
+    /// `#` are dropped, and empty lines are taken as empty context lines.
+    pub fn edited(&self, text: &str) -> Result<Hunk> {
+        let mut lines = vec![];
+        for line in text.lines() {
+            match line.chars().next() {
+                Some('#') => {}
+                None => lines.push(" ".to_string()),
+                Some(' ' | '+' | '-' | '\\') => lines.push(line.to_string()),
+                Some(_) => bail!(
+                    "Invalid line in the edited hunk, it must start with ' ', '+' or '-': {}",
+                    line
+                ),
+            }
+        }
+        let hunk = Hunk {
+            old_start: self.old_start,
+            new_start: self.new_start,
+            section: self.section.clone(),
+            ..Hunk::counted(lines)
+        };
+        if !hunk.lines.iter().any(|line| line.starts_with(['+', '-'])) {
+            bail!("The edited hunk has no changes left");
+        }
+        Ok(hunk)
+    }
+
+    /// `self` and `next` as one hunk, when `next` starts where `self` ends or within the
+    /// context at its end. `None` when they're apart, or when `next` changes lines `self`
+    /// covers too.
+    fn joined(&self, next: &Hunk) -> Option<Hunk> {
+        let end = self.old_start + self.old_len;
+        if next.old_start < self.old_start || next.old_start > end {
+            return None;
+        }
+        // the lines of `next` that `self` covers already
+        let mut rest = next.lines.as_slice();
+        for _ in next.old_start..end {
+            match rest.split_first() {
+                Some((line, tail)) if line.starts_with(' ') => rest = tail,
+                _ => return None,
+            }
+        }
+        if next.old_start < end {
+            // with the "\ No newline at end of file" of the last one
+            while rest.first().is_some_and(|line| line.starts_with('\\')) {
+                rest = &rest[1..];
+            }
+        }
+        let mut lines = self.lines.clone();
+        lines.extend_from_slice(rest);
+        Some(Hunk {
+            old_start: self.old_start,
+            section: self.section.clone(),
+            ..Hunk::counted(lines)
+        })
+    }
+
+    /// A hunk of `lines` with the lengths they add up to.
+    fn counted(lines: Vec<String>) -> Hunk {
+        let count = |prefixes: [char; 2]| {
+            lines
+                .iter()
+                .filter(|line| line.starts_with(prefixes))
+                .count() as u32
+        };
+        Hunk {
+            old_start: 0,
+            old_len: count([' ', '-']),
+            new_start: 0,
+            new_len: count([' ', '+']),
+            section: String::new(),
+            lines,
+        }
+    }
+
+    /// Prints the hunk under `title` with its added and removed lines colored.
+    pub fn print(&self, title: &str) -> Result<()> {
+        colorful_print(
+            Styles::with_bold(theme().background, theme().prompt),
+            format!("\n{}\n", title),
+        )?;
+        print_diff(&self.to_string())
+    }
+
+    /// The `@@ ... @@` line.
+    pub fn header(&self) -> String {
+        let range = |start: u32, len: u32| {
+            // an empty range is written as the line before it
+            let start = if len == 0 {
+                start.saturating_sub(1)
+            } else {
+                start
+            };
+            format!("{},{}", start, len)
+        };
+        let header = format!(
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_len),
+            range(self.new_start, self.new_len)
+        );
+        if self.section.is_empty() {
+            header
+        } else {
+            format!("{} {}", header, self.section)
+        }
+    }
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header())?;
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints `diff` with its headers, added and removed lines colored.
+pub fn print_diff(diff: &str) -> Result<()> {
+    let mut in_hunk = false;
+    for line in diff.lines() {
+        if line.starts_with("diff ") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        }
+        let color = match line.chars().next() {
+            _ if !in_hunk || line.starts_with("@@") => theme().command_notice,
+            Some('+') => theme().success,
+            Some('-') => theme().error,
+            _ => theme().output,
+        };
+        colorful_print(
+            Styles::new(theme().background, color),
+            format!("{}\n", line),
+        )?;
+    }
+    Ok(())
+}
+
+/// Parses `start,len`, or `start` for a single line. An empty range names the line before it.
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let (start, len) = match range.split_once(',') {
+        Some((start, len)) => (start.parse().ok()?, len.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    Some((if len == 0 { start + 1 } else { start }, len))
+}
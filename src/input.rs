@@ -1,9 +1,198 @@
-use std::io;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt, fs,
+    io::{self, BufRead},
+    path::Path,
+};
 
-use anyhow::{Context, Result};
-use crossterm::{terminal::disable_raw_mode, terminal::enable_raw_mode};
+use anyhow::{bail, Context, Result};
+use crossterm::{
+    event::{read, Event, KeyCode},
+    terminal::disable_raw_mode,
+    terminal::enable_raw_mode,
+};
 
-use crate::output::output_notice;
+use serde_json::json;
+
+use crate::output::{emit, is_json, output_notice};
+
+/// Where key presses and lines typed by the user come from.
+pub trait InputSource {
+    fn read_key(&mut self) -> Result<KeyCode>;
+    fn read_line(&mut self) -> Result<String>;
+}
+
+/// Reads keys in raw mode and lines from stdin, as an interactive session does.
+#[derive(Debug, Default)]
+pub struct Terminal;
+
+impl InputSource for Terminal {
+    fn read_key(&mut self) -> Result<KeyCode> {
+        loop {
+            enable_raw_input()?;
+            if let Event::Key(event) = read().context("Failed to read key")? {
+                return Ok(event.code);
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .context("Failed to read line")?;
+        Ok(input.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Replays a fixed sequence of inputs, one entry per key press or per line.
+#[derive(Debug, Default)]
+pub struct Scripted {
+    inputs: VecDeque<String>,
+}
+
+impl Scripted {
+    pub fn new<S: AsRef<str>>(inputs: &[S]) -> Self {
+        Self {
+            inputs: inputs.iter().map(|s| s.as_ref().to_string()).collect(),
+        }
+    }
+
+    /// Splits `keys` on commas, e.g. `a,y,c,i`. A literal comma is written `\,`.
+    pub fn from_keys(keys: &str) -> Self {
+        let mut inputs = VecDeque::new();
+        let mut current = String::new();
+        let mut chars = keys.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => current.extend(chars.next()),
+                ',' => inputs.push_back(current.split_off(0)),
+                c => current.push(c),
+            }
+        }
+        if !current.is_empty() || keys.ends_with(',') {
+            inputs.push_back(current);
+        }
+        Self { inputs }
+    }
+
+    /// Reads a replay file holding one input per line; blank lines and lines starting with `#`
+    /// are skipped.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read replay file {}", path.display()))?;
+        let inputs = content
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        Ok(Self { inputs })
+    }
+
+    fn next(&mut self) -> Result<String> {
+        Ok(self.inputs.pop_front().ok_or(ScriptExhausted)?)
+    }
+}
+
+impl InputSource for Scripted {
+    fn read_key(&mut self) -> Result<KeyCode> {
+        parse_key(&self.next()?)
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        self.next()
+    }
+}
+
+/// Reads one input per line from stdin, so a flow can be piped in.
+#[derive(Debug, Default)]
+pub struct StdinLines;
+
+impl StdinLines {
+    fn next(&mut self) -> Result<String> {
+        let mut input = String::new();
+        let read = io::stdin()
+            .lock()
+            .read_line(&mut input)
+            .context("Failed to read line")?;
+        if read == 0 {
+            bail!(ScriptExhausted);
+        }
+        Ok(input.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+impl InputSource for StdinLines {
+    fn read_key(&mut self) -> Result<KeyCode> {
+        parse_key(&self.next()?)
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        self.next()
+    }
+}
+
+/// Returned once every scripted input has been consumed.
+#[derive(Debug)]
+pub struct ScriptExhausted;
+
+impl fmt::Display for ScriptExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("No scripted input left")
+    }
+}
+
+impl std::error::Error for ScriptExhausted {}
+
+/// Parses a single character or a key name such as `enter`, `esc` or `up`.
+pub fn parse_key(input: &str) -> Result<KeyCode> {
+    let mut chars = input.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    Ok(match input.to_ascii_lowercase().as_str() {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => bail!("Invalid scripted key: {}", input),
+    })
+}
+
+thread_local! {
+    static SOURCE: RefCell<Box<dyn InputSource>> = RefCell::new(Box::new(Terminal));
+}
+
+/// Makes every following prompt read from `source`.
+pub fn set_source(source: impl InputSource + 'static) {
+    SOURCE.with(|current| *current.borrow_mut() = Box::new(source));
+}
+
+/// Answers the following prompts with `inputs` instead of the terminal.
+pub fn script<S: AsRef<str>>(inputs: &[S]) {
+    set_source(Scripted::new(inputs));
+}
+
+/// Leaves raw mode when dropped, so the terminal is usable again however a flow ends,
+/// including by a panic.
+#[derive(Debug)]
+pub struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
 
 pub fn enable_raw_input() -> Result<()> {
     enable_raw_mode().context("Failed to enable raw mode")
@@ -13,16 +202,22 @@ pub fn disable_raw_input() -> Result<()> {
     disable_raw_mode().context("Failed to disable raw mode")
 }
 
+pub fn read_key() -> Result<KeyCode> {
+    SOURCE.with(|source| source.borrow_mut().read_key())
+}
+
 pub fn read_line(notice: &str) -> Result<String> {
     disable_raw_input()?;
-    output_notice(notice)?;
-    output_notice("\n")?;
+    if is_json() {
+        emit("prompt", json!({"prompt": notice.trim(), "line": true}))?;
+    } else {
+        output_notice(notice)?;
+        output_notice("\n")?;
+    }
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .context("Failed to read line")?;
-    Ok(input)
+    let line = SOURCE.with(|source| source.borrow_mut().read_line())?;
+    emit("choice", json!({"prompt": notice.trim(), "value": line}))?;
+    Ok(line)
 }
 
 pub fn read_line_simple() -> Result<String> {
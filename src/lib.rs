@@ -1,8 +1,15 @@
 pub mod args;
+pub mod backend;
 pub mod commands;
+pub mod config;
 pub mod flow;
 pub mod git;
+pub mod hunks;
 pub mod input;
+pub mod message;
+pub mod next;
 pub mod options;
 pub mod output;
 pub mod status;
+pub mod summary;
+pub mod theme;
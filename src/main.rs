@@ -1,7 +1,89 @@
-use gitflow::{args::Args, flow};
+use std::{
+    io::{stdout, IsTerminal},
+    process,
+};
+
+#[cfg(not(feature = "native"))]
+use gitflow::backend::CliBackend;
+#[cfg(feature = "native")]
+use gitflow::backend::NativeBackend;
+use gitflow::{
+    args::{Args, Command, OutputFormat},
+    backend::{DryRunBackend, GitBackend},
+    commands::set_dry_run,
+    config::{self, Config},
+    flow::{self, EXIT_QUIT},
+    input::ScriptExhausted,
+    next::next,
+    output::{emit, output_environment, set_json, set_styled, set_theme, set_verbosity},
+    summary,
+    theme::{no_color, Theme},
+};
+use serde_json::json;
 
 fn main() {
     let args = Args::new();
+    set_json(args.output == OutputFormat::Json);
+    set_styled(stdout().is_terminal());
+    set_verbosity(args.verbosity());
+    set_dry_run(args.dry_run);
+    let code = run(args);
+    // the exit code is all that's left to report
+    let _ = emit("exit", json!({ "code": code }));
+    process::exit(code);
+}
+
+fn run(args: Args) -> i32 {
+    if let Err(err) = args
+        .install_input_source()
+        .and_then(|()| output_environment())
+    {
+        return report(err);
+    }
+
+    #[cfg(feature = "native")]
+    let backend = NativeBackend::default();
+    #[cfg(not(feature = "native"))]
+    let backend = CliBackend;
+    let backend: Box<dyn GitBackend> = if args.dry_run {
+        Box::new(DryRunBackend::new(backend))
+    } else {
+        Box::new(backend)
+    };
+    match Config::load(&*backend).and_then(|mut config| {
+        config.merge_args(&args);
+        let theme = Theme::from_config(&config)?;
+        set_theme(if no_color() { Theme::MONOCHROME } else { theme });
+        Ok(config)
+    }) {
+        Ok(config) => config::install(config),
+        Err(err) => return report(err),
+    }
+
+    match args.command {
+        Some(Command::Next { json }) => match next(&*backend, json) {
+            Ok(()) => EXIT_QUIT,
+            Err(err) => report(err),
+        },
+        Some(Command::Status) => match summary::show(&*backend) {
+            Ok(()) => EXIT_QUIT,
+            Err(err) => report(err),
+        },
+        None => match flow::run(&*backend, args) {
+            Ok(outcome) => outcome.exit_code(),
+            // an unattended run ends once its scripted input is used up
+            Err(err) if err.is::<ScriptExhausted>() => EXIT_QUIT,
+            Err(err) => report(err),
+        },
+    }
+}
 
-    flow::run(args).unwrap();
+fn report(err: anyhow::Error) -> i32 {
+    eprintln!("Error: {:#}", err);
+    let code = flow::exit_code(&err);
+    let _ = emit(
+        "error",
+        json!({ "message": format!("{:#}", err), "exit_code": code }),
+    );
+    code
 }
@@ -0,0 +1,71 @@
+use crate::status::StatusEntry;
+
+/// The text the editor opens with to write a commit message: `message`, then what's about
+/// to be committed, as comments.
+pub fn template(message: &str, staged: &[&StatusEntry]) -> String {
+    let mut text = message.trim_end().to_string();
+    text.push_str(
+        "\n\n\
+         # Please enter the commit message for your changes. Lines starting\n\
+         # with '#' will be ignored, and an empty message aborts the commit.\n\
+         #\n\
+         # Changes to be committed:\n",
+    );
+    for entry in staged {
+        let path = match &entry.orig_path {
+            Some(orig_path) => format!("{} -> {}", orig_path, entry.path),
+            None => entry.path.clone(),
+        };
+        text.push_str(&format!(
+            "#\t{:<12}{}\n",
+            format!("{}:", entry.index.describe()),
+            path
+        ));
+    }
+    text
+}
+
+/// `message` followed by the comments and body of `template`, whose subject line is left out
+/// as `message` has one already.
+pub fn with_template(message: &str, template: &str) -> String {
+    let mut text = message.trim_end().to_string();
+    text.push_str("\n\n");
+    let mut lines = template.lines();
+    // the comments and blank lines before the subject
+    for line in lines.by_ref() {
+        if !line.starts_with('#') && !line.trim().is_empty() {
+            break;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
+
+/// The message left in `text`, as `git commit --cleanup=strip` takes it: comment lines and
+/// trailing whitespace are dropped, and runs of blank lines become one.
+pub fn strip(text: &str) -> String {
+    let mut message = String::new();
+    let mut blank = false;
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank = !message.is_empty();
+            continue;
+        }
+        if blank {
+            message.push('\n');
+            blank = false;
+        }
+        message.push_str(line);
+        message.push('\n');
+    }
+    message.trim_end().to_string()
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde_json::json;
+
+use crate::{
+    backend::GitBackend,
+    flow::{self, Menu},
+    output::{emit, is_json, set_quiet},
+    status::GitStatus,
+};
+
+/// Prints the status and the options the flow would offer in it, the recommended one first,
+/// without asking anything. With JSON output they are a `next` event.
+pub fn next(git: &dyn GitBackend, json: bool) -> Result<()> {
+    // only the result goes to stdout
+    set_quiet(true);
+    let snapshot = flow::capture(git)?;
+    let status = GitStatus::of(snapshot.as_ref());
+    let menu = flow::menu(git, snapshot.as_ref())?;
+    if is_json() {
+        emit("next", to_json(status, &menu))?;
+    } else if json {
+        println!("{}", to_json(status, &menu));
+    } else {
+        print!("{}", to_text(status, &menu));
+    }
+    Ok(())
+}
+
+fn recommended_key(menu: &Menu) -> Option<char> {
+    menu.recommended().map(|option| option.key)
+}
+
+pub fn to_text(status: GitStatus, menu: &Menu) -> String {
+    let recommended = recommended_key(menu);
+    let mut text = format!("{:?}: {}\n", status, menu.prompt);
+    for option in &menu.options {
+        let marker = if Some(option.key) == recommended {
+            '*'
+        } else {
+            ' '
+        };
+        text.push_str(&format!("{} [{}] {}\n", marker, option.key, option.desc));
+    }
+    text
+}
+
+pub fn to_json(status: GitStatus, menu: &Menu) -> serde_json::Value {
+    let recommended = recommended_key(menu);
+    json!({
+        "status": format!("{:?}", status),
+        "prompt": menu.prompt,
+        "recommended": recommended.map(String::from),
+        "actions": menu
+            .options
+            .iter()
+            .map(|option| json!({
+                "key": option.key.to_string(),
+                "description": option.desc,
+                "recommended": Some(option.key) == recommended,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
@@ -1,9 +1,16 @@
-use std::{collections::HashMap, process};
+use std::{cell::RefCell, collections::HashMap, fmt, io::stdout};
 
-use anyhow::Result;
-use crossterm::event::{read, Event, KeyCode};
+use anyhow::{bail, Result};
+use crossterm::{
+    cursor::MoveToPreviousLine,
+    event::KeyCode,
+    style::Color,
+    terminal::{Clear, ClearType},
+    ExecutableCommand,
+};
+use serde_json::json;
 
-use crate::{commands::quit, input::enable_raw_input, output::*};
+use crate::{config, input::read_key, output::*};
 
 pub struct OptionItem<'a, T> {
     pub key: char,
@@ -16,91 +23,673 @@ pub struct Options<'a, T> {
 }
 
 impl<'a, T> Options<'a, T> {
+    /// Asks for an option with the first one highlighted.
     pub fn execute(&self) -> Result<T> {
+        let default = self.options.first().map_or('q', |option| option.key);
+        self.execute_with_default(default)
+    }
+
+    /// Asks for an option with the one of `key` highlighted. An option is picked by its key,
+    /// or by moving the highlight with Up/Down (or `j`/`k` when no option uses them),
+    /// Home and End, then pressing Enter. Esc fails with [`Navigation::Back`] and `q` with
+    /// [`Navigation::Quit`]; when an action goes back, the prompt is asked again. The
+    /// letter keys follow the configured [`KeyBindings`](config::KeyBindings).
+    pub fn execute_with_default(&self, key: char) -> Result<T> {
+        let keys = config::current().keys;
         self.print_prompt()?;
-        self.print_options()?;
 
         let ops_map: HashMap<_, _> = self
             .options
             .iter()
-            .map(|option| (option.key.to_ascii_lowercase(), &option.action))
+            .map(|option| (option.key.to_ascii_lowercase(), option))
             .collect();
+        // the row after the options is Quit
+        let last = self.options.len();
+        let mut selected = self
+            .options
+            .iter()
+            .position(|option| option.key.eq_ignore_ascii_case(&key))
+            .unwrap_or(last);
+        let mut invalid = false;
+        let mut drawn = 0;
 
         loop {
-            enable_raw_input().unwrap();
-            if let Ok(Event::Key(event)) = read() {
-                if let KeyCode::Char(c) = event.code {
+            drawn = self.render(selected, invalid, drawn)?;
+            invalid = false;
+            match read_key()? {
+                KeyCode::Char(c) => {
                     let c = c.to_ascii_lowercase();
-                    if c == 'q' {
-                        quit()?;
-                        process::exit(0);
+                    if c == keys.quit() {
+                        bail!(Navigation::Quit);
                     }
-                    let option = ops_map.get(&c);
-                    match option {
-                        Some(option) => {
-                            return option();
-                        }
-                        None => {
-                            output_notice("Invalid option, please try again\n.")?;
-                        }
+                    match (ops_map.get(&c), c) {
+                        (Some(option), _) => match self.run(option)? {
+                            Some(result) => return Ok(result),
+                            None => {
+                                self.print_prompt()?;
+                                drawn = 0;
+                            }
+                        },
+                        (None, c) if c == keys.down() => selected = (selected + 1).min(last),
+                        (None, c) if c == keys.up() => selected = selected.saturating_sub(1),
+                        (None, _) => invalid = true,
                     }
                 }
+                KeyCode::Down => selected = (selected + 1).min(last),
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Home => selected = 0,
+                KeyCode::End => selected = last,
+                KeyCode::Enter => match self.options.get(selected) {
+                    Some(option) => match self.run(option)? {
+                        Some(result) => return Ok(result),
+                        None => {
+                            self.print_prompt()?;
+                            drawn = 0;
+                        }
+                    },
+                    None => bail!(Navigation::Quit),
+                },
+                KeyCode::Esc => bail!(Navigation::Back),
+                _ => {}
             }
         }
     }
 
-    fn print_prompt(&self) -> Result<()> {
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_FG_COLOR),
-            format!("\n==> {}\n", self.prompt),
-        )
+    /// Runs the action of `option` with it added to the breadcrumbs, `None` when the user
+    /// went back from a prompt it asked.
+    fn run(&self, option: &OptionItem<'a, T>) -> Result<Option<T>> {
+        emit(
+            "choice",
+            json!({
+                "prompt": self.prompt,
+                "key": option.key.to_string(),
+                "description": option.desc,
+            }),
+        )?;
+        let _crumb = Crumb::push(&option.desc);
+        match (option.action)() {
+            Err(err) if Navigation::of(&err) == Some(Navigation::Back) => Ok(None),
+            result => result.map(Some),
+        }
     }
 
-    fn print_options(&self) -> Result<()> {
-        self.options
-            .iter()
-            .try_for_each(|option| Self::print_option(option))?;
-        Self::print_quit()?;
-        Ok(())
+    fn print_prompt(&self) -> Result<()> {
+        emit(
+            "prompt",
+            json!({
+                "prompt": self.prompt,
+                "breadcrumbs": breadcrumbs(),
+                "options": self
+                    .options
+                    .iter()
+                    .map(|option| json!({"key": option.key.to_string(), "description": option.desc}))
+                    .collect::<Vec<_>>(),
+            }),
+        )?;
+        print_prompt(self.prompt)
     }
 
-    fn print_option(option: &OptionItem<'a, T>) -> Result<()> {
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            "\n\t- [".to_string(),
-        )?;
-        colorful_print(
-            Styles::with_bold(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_KEY_FG_COLOR),
-            option.key.to_string(),
-        )?;
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            "]: ".to_string(),
+    /// Draws the options over the `drawn` lines of the previous render and returns how many
+    /// lines it drew.
+    fn render(&self, selected: usize, invalid: bool, drawn: u16) -> Result<u16> {
+        clear_lines(drawn)?;
+        for (idx, option) in self.options.iter().enumerate() {
+            print_item(
+                &option.key.to_string(),
+                theme().option_key,
+                &option.desc,
+                idx == selected,
+            )?;
+        }
+        print_item(
+            &quit_key(),
+            theme().quit_key,
+            "Quit",
+            selected == self.options.len(),
         )?;
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            format!("{}\n", option.desc).to_string(),
+        let mut lines = 2 * (self.options.len() as u16 + 1);
+        if invalid {
+            output_notice("Invalid option, please try again.\n")?;
+            lines += 2;
+        }
+        Ok(lines)
+    }
+}
+
+/// How many items a [`Picker`] shows at once.
+const PAGE_SIZE: usize = 10;
+
+/// Picks one item of a list that may be too long for single-key [`Options`]. Items are chosen
+/// by number, followed by Enter when more items start with the typed digits, and shown a page
+/// at a time.
+pub struct Picker<'a> {
+    pub prompt: &'a str,
+    pub items: Vec<String>,
+}
+
+impl<'a> Picker<'a> {
+    pub fn pick(&self) -> Result<String> {
+        if self.items.is_empty() {
+            bail!("Nothing to choose from");
+        }
+        let pages = self.items.len().div_ceil(PAGE_SIZE);
+        let mut page = 0;
+        let mut number = String::new();
+        emit(
+            "prompt",
+            json!({"prompt": self.prompt, "breadcrumbs": breadcrumbs(), "items": self.items}),
         )?;
-        Ok(())
+        self.print_page(page, pages)?;
+
+        loop {
+            match read_key()? {
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    number.push(c);
+                    match number.parse::<usize>() {
+                        Ok(n) if (1..=self.items.len()).contains(&n) => {
+                            // no longer number starts with these digits
+                            if n * 10 > self.items.len() {
+                                return self.chosen(n);
+                            }
+                            echo(&format!("\r{}", number))?;
+                        }
+                        _ => {
+                            number.clear();
+                            output_notice("\nInvalid number, please try again.\n")?;
+                        }
+                    }
+                }
+                KeyCode::Enter if !number.is_empty() => {
+                    return self.chosen(number.parse()?);
+                }
+                KeyCode::Backspace | KeyCode::Esc if !number.is_empty() => {
+                    number.clear();
+                    echo("\r      \r")?;
+                }
+                KeyCode::Esc => bail!(Navigation::Back),
+                KeyCode::Char('>') | KeyCode::Right | KeyCode::PageDown if page + 1 < pages => {
+                    page += 1;
+                    number.clear();
+                    self.print_page(page, pages)?;
+                }
+                KeyCode::Char('<') | KeyCode::Left | KeyCode::PageUp if page > 0 => {
+                    page -= 1;
+                    number.clear();
+                    self.print_page(page, pages)?;
+                }
+                KeyCode::Char(c) if c.to_ascii_lowercase() == config::current().keys.quit() => {
+                    bail!(Navigation::Quit)
+                }
+                _ => {
+                    output_notice("Invalid option, please try again.\n")?;
+                }
+            }
+        }
     }
 
-    fn print_quit() -> Result<()> {
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            "\n\t- [".to_string(),
+    /// The item numbered `n`, counting from 1.
+    fn chosen(&self, n: usize) -> Result<String> {
+        let item = self.items[n - 1].clone();
+        emit("choice", json!({"prompt": self.prompt, "value": item}))?;
+        Ok(item)
+    }
+
+    fn print_page(&self, page: usize, pages: usize) -> Result<()> {
+        print_prompt(self.prompt)?;
+        let start = page * PAGE_SIZE;
+        for (idx, item) in self.items.iter().enumerate().skip(start).take(PAGE_SIZE) {
+            print_item(&(idx + 1).to_string(), theme().option_key, item, false)?;
+        }
+        if pages > 1 {
+            output_notice(&format!(
+                "\nPage {}/{}, [<] previous page, [>] next page.\n",
+                page + 1,
+                pages
+            ))?;
+        }
+        print_item(&quit_key(), theme().quit_key, "Quit", false)
+    }
+}
+
+/// One entry of a [`Finder`].
+pub struct Candidate {
+    pub value: String,
+    /// Shown under the list while the candidate is highlighted.
+    pub preview: Option<String>,
+    /// Orders equally good matches, highest first, e.g. a commit date.
+    pub recency: i64,
+}
+
+/// Picks one of many candidates by typing part of it. Matches are ranked by how well they
+/// fit the query, then by recency; Up and Down move the highlight, Enter picks it, and Esc
+/// clears the query or goes back when it's already empty.
+pub struct Finder<'a> {
+    pub prompt: &'a str,
+    pub candidates: Vec<Candidate>,
+}
+
+impl<'a> Finder<'a> {
+    pub fn find(&self) -> Result<String> {
+        if self.candidates.is_empty() {
+            bail!("Nothing to choose from");
+        }
+        emit(
+            "prompt",
+            json!({
+                "prompt": self.prompt,
+                "breadcrumbs": breadcrumbs(),
+                "items": self
+                    .candidates
+                    .iter()
+                    .map(|candidate| &candidate.value)
+                    .collect::<Vec<_>>(),
+            }),
         )?;
+        print_prompt(self.prompt)?;
+
+        let mut query = String::new();
+        let mut selected = 0;
+        let mut drawn = 0;
+        loop {
+            let matches = self.matches(&query);
+            selected = selected.min(matches.len().saturating_sub(1));
+            drawn = self.render(&query, &matches, selected, drawn)?;
+
+            match read_key()? {
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    selected = 0;
+                }
+                KeyCode::Backspace => {
+                    query.pop();
+                    selected = 0;
+                }
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Tab => {
+                    selected = (selected + 1).min(matches.len().min(PAGE_SIZE).saturating_sub(1))
+                }
+                KeyCode::Enter => {
+                    if let Some(candidate) = matches.get(selected) {
+                        emit(
+                            "choice",
+                            json!({"prompt": self.prompt, "value": candidate.value}),
+                        )?;
+                        return Ok(candidate.value.clone());
+                    }
+                }
+                KeyCode::Esc if query.is_empty() => bail!(Navigation::Back),
+                KeyCode::Esc => query.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    /// The candidates matching `query`, best first.
+    fn matches(&self, query: &str) -> Vec<&Candidate> {
+        let mut matches: Vec<_> = self
+            .candidates
+            .iter()
+            .filter_map(|candidate| {
+                fuzzy_score(query, &candidate.value).map(|score| (score, candidate))
+            })
+            .collect();
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.cmp(a_score).then(b.recency.cmp(&a.recency))
+        });
+        matches
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    /// Draws over the `drawn` lines of the previous render and returns how many lines it drew.
+    fn render(
+        &self,
+        query: &str,
+        matches: &[&Candidate],
+        selected: usize,
+        drawn: u16,
+    ) -> Result<u16> {
+        clear_lines(drawn)?;
+        let mut lines = 1;
         colorful_print(
-            Styles::with_bold(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_QUITKEY_FG_COLOR),
-            "Q".to_string(),
+            Styles::with_bold(theme().background, theme().option_key),
+            format!("> {}\n", query),
         )?;
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            "]: ".to_string(),
+        for (idx, candidate) in matches.iter().take(PAGE_SIZE).enumerate() {
+            let (marker, color) = if idx == selected {
+                ("> ", theme().option_key)
+            } else {
+                ("  ", theme().option)
+            };
+            colorful_print(
+                Styles::new(theme().background, color),
+                format!("{}{}\n", marker, candidate.value),
+            )?;
+            lines += 1;
+        }
+        if matches.len() > PAGE_SIZE {
+            echo(&format!("  ... {} more\n", matches.len() - PAGE_SIZE))?;
+            lines += 1;
+        }
+        if let Some(preview) = matches.get(selected).and_then(|c| c.preview.as_ref()) {
+            colorful_print(
+                Styles::new(theme().background, theme().command),
+                format!("\n{}\n", preview),
+            )?;
+            lines += 2;
+        }
+        Ok(lines)
+    }
+}
+
+/// One entry of a [`Checklist`].
+pub struct Check {
+    pub value: String,
+    /// Shown before the value and matched by the filters, e.g. `modified`.
+    pub label: String,
+}
+
+/// A key showing only the [`Check`]s of one label.
+pub struct CheckFilter<'a> {
+    pub key: char,
+    pub label: &'a str,
+}
+
+/// Picks any number of items. Space checks or unchecks the highlighted item, `a` all the
+/// items shown, and a filter key shows only the items with its label until it's pressed
+/// again. Enter confirms the checked items, or the highlighted one when none is checked.
+pub struct Checklist<'a> {
+    pub prompt: &'a str,
+    pub items: Vec<Check>,
+    pub filters: Vec<CheckFilter<'a>>,
+}
+
+impl<'a> Checklist<'a> {
+    pub fn select(&self) -> Result<Vec<String>> {
+        if self.items.is_empty() {
+            bail!("Nothing to choose from");
+        }
+        emit(
+            "prompt",
+            json!({
+                "prompt": self.prompt,
+                "breadcrumbs": breadcrumbs(),
+                "items": self.items.iter().map(|item| &item.value).collect::<Vec<_>>(),
+                "multiple": true,
+            }),
         )?;
+        print_prompt(self.prompt)?;
+
+        let keys = config::current().keys;
+        let mut checked = vec![false; self.items.len()];
+        let mut filter: Option<&CheckFilter> = None;
+        let mut selected = 0;
+        let mut drawn = 0;
+        loop {
+            let shown: Vec<usize> = (0..self.items.len())
+                .filter(|&idx| filter.is_none_or(|f| self.items[idx].label == f.label))
+                .collect();
+            let last = shown.len().saturating_sub(1);
+            selected = selected.min(last);
+            drawn = self.render(&shown, &checked, selected, filter, drawn)?;
+
+            match read_key()? {
+                KeyCode::Char(' ') => {
+                    if let Some(&idx) = shown.get(selected) {
+                        checked[idx] = !checked[idx];
+                    }
+                }
+                KeyCode::Char(c) => {
+                    let c = c.to_ascii_lowercase();
+                    if c == keys.quit() {
+                        bail!(Navigation::Quit);
+                    } else if c == 'a' {
+                        let all = shown.iter().all(|&idx| checked[idx]);
+                        shown.iter().for_each(|&idx| checked[idx] = !all);
+                    } else if let Some(pressed) = self.filters.iter().find(|f| f.key == c) {
+                        filter = match filter {
+                            Some(current) if current.key == c => None,
+                            _ => Some(pressed),
+                        };
+                        selected = 0;
+                    } else if c == keys.down() {
+                        selected = (selected + 1).min(last);
+                    } else if c == keys.up() {
+                        selected = selected.saturating_sub(1);
+                    }
+                }
+                KeyCode::Down => selected = (selected + 1).min(last),
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Home => selected = 0,
+                KeyCode::End => selected = last,
+                KeyCode::Enter => {
+                    let mut values: Vec<_> = (0..self.items.len())
+                        .filter(|&idx| checked[idx])
+                        .map(|idx| self.items[idx].value.clone())
+                        .collect();
+                    if values.is_empty() {
+                        let Some(&idx) = shown.get(selected) else {
+                            continue;
+                        };
+                        values.push(self.items[idx].value.clone());
+                    }
+                    emit("choice", json!({"prompt": self.prompt, "values": values}))?;
+                    return Ok(values);
+                }
+                KeyCode::Esc => bail!(Navigation::Back),
+                _ => {}
+            }
+        }
+    }
+
+    /// Draws the items of `shown` over the `drawn` lines of the previous render and returns
+    /// how many lines it drew.
+    fn render(
+        &self,
+        shown: &[usize],
+        checked: &[bool],
+        selected: usize,
+        filter: Option<&CheckFilter>,
+        drawn: u16,
+    ) -> Result<u16> {
+        clear_lines(drawn)?;
+        // keep the highlighted item on screen
+        let start = (selected + 1).saturating_sub(PAGE_SIZE);
+        let mut lines = 0;
+        for (row, &idx) in shown.iter().enumerate().skip(start).take(PAGE_SIZE) {
+            let item = &self.items[idx];
+            let (marker, color) = if row == selected {
+                ("> ", theme().option_key)
+            } else {
+                ("  ", theme().option)
+            };
+            colorful_print(
+                Styles::new(theme().background, color),
+                format!(
+                    "{}[{}] {:<12}{}\n",
+                    marker,
+                    if checked[idx] { 'x' } else { ' ' },
+                    format!("{}:", item.label),
+                    item.value
+                ),
+            )?;
+            lines += 1;
+        }
+        if shown.len() > start + PAGE_SIZE {
+            echo(&format!("  ... {} more\n", shown.len() - start - PAGE_SIZE))?;
+            lines += 1;
+        }
+        let filters: Vec<_> = self
+            .filters
+            .iter()
+            .map(|f| format!("[{}] {}", f.key.to_ascii_uppercase(), f.label))
+            .collect();
+        echo(&format!(
+            "\n{} of {} checked{}. [Space] check, [A] all, {}, [Enter] done.\n",
+            checked.iter().filter(|&&checked| checked).count(),
+            self.items.len(),
+            filter.map_or(String::new(), |f| format!(", showing {}", f.label)),
+            filters.join(", ")
+        ))?;
+        Ok(lines + 2)
+    }
+}
+
+/// Scores how well `candidate` matches `query`, whose characters must appear in it in order,
+/// ignoring case. Consecutive characters and characters starting a word score higher, gaps
+/// between them lower. `None` when it doesn't match at all.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let Some(first) = query.first() else {
+        return Some(0);
+    };
+    // matching greedily from each occurrence of the first character finds e.g. the second
+    // `f` of `fix/flaky` for `fl`
+    (0..chars.len())
+        .filter(|&start| chars[start].eq_ignore_ascii_case(first))
+        .filter_map(|start| score_from(&query, &chars, start))
+        .max()
+}
+
+fn score_from(query: &[char], chars: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut from = start;
+    let mut previous: Option<usize> = None;
+    for q in query {
+        let idx = (from..chars.len()).find(|&i| chars[i].eq_ignore_ascii_case(q))?;
+        score += 1;
+        if idx == 0 || matches!(chars[idx - 1], '/' | '-' | '_' | '.') {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if previous + 1 == idx => score += 4,
+            // opening a gap costs more than widening it
+            Some(previous) => score -= 3 + (idx - previous - 2) as i64 / 2,
+            None => {}
+        }
+        previous = Some(idx);
+        from = idx + 1;
+    }
+    Some(score)
+}
+
+/// Returned by a prompt the user left instead of answering it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Navigation {
+    /// Back to the previous prompt, which asks again.
+    Back,
+    /// Leave gitflow.
+    Quit,
+}
+
+impl Navigation {
+    pub fn of(err: &anyhow::Error) -> Option<Self> {
+        err.downcast_ref().copied()
+    }
+}
+
+impl fmt::Display for Navigation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Navigation::Back => f.write_str("Went back"),
+            Navigation::Quit => f.write_str("Quit"),
+        }
+    }
+}
+
+impl std::error::Error for Navigation {}
+
+thread_local! {
+    static BREADCRUMBS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+/// Keeps an option in the breadcrumbs while its action runs.
+struct Crumb;
+
+impl Crumb {
+    fn push(desc: &str) -> Self {
+        let desc = desc.trim_end_matches(['.', '!', ' ']).to_string();
+        BREADCRUMBS.with(|crumbs| crumbs.borrow_mut().push(desc));
+        Crumb
+    }
+}
+
+impl Drop for Crumb {
+    fn drop(&mut self) {
+        BREADCRUMBS.with(|crumbs| crumbs.borrow_mut().pop());
+    }
+}
+
+/// The options picked to reach the current prompt, outermost first.
+pub fn breadcrumbs() -> Vec<String> {
+    BREADCRUMBS.with(|crumbs| crumbs.borrow().clone())
+}
+
+/// Prints where the user is, then `prompt`.
+fn print_prompt(prompt: &str) -> Result<()> {
+    let crumbs = breadcrumbs();
+    if !crumbs.is_empty() {
         colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_OPTIONI_DESC_FG_COLOR),
-            format!("{}\n", "Quit").to_string(),
+            Styles::new(theme().background, theme().notice),
+            format!("\n{}  [Esc]: Back", crumbs.join(" > ")),
         )?;
-        Ok(())
     }
+    colorful_print(
+        Styles::new(theme().background, theme().prompt),
+        format!("\n==> {}\n", prompt),
+    )
+}
+
+/// Prints what is being typed or drawn, which isn't worth a message of its own.
+fn echo(text: &str) -> Result<()> {
+    colorful_print(
+        Styles::new(theme().background, theme().notice),
+        text.to_string(),
+    )
+}
+
+/// Erases the last `lines` lines so a menu can be drawn again in place.
+fn clear_lines(lines: u16) -> Result<()> {
+    // nothing was drawn in JSON output
+    if lines > 0 && !is_json() {
+        stdout()
+            .execute(MoveToPreviousLine(lines))?
+            .execute(Clear(ClearType::FromCursorDown))?;
+    }
+    Ok(())
+}
+
+/// The label of the Quit row.
+fn quit_key() -> String {
+    config::current()
+        .keys
+        .quit()
+        .to_ascii_uppercase()
+        .to_string()
+}
+
+fn print_item(key: &str, key_color: Option<Color>, desc: &str, selected: bool) -> Result<()> {
+    let (marker, desc_color) = if selected {
+        (">", theme().option_key)
+    } else {
+        ("-", theme().option)
+    };
+    colorful_print(
+        Styles::new(theme().background, theme().option),
+        format!("\n\t{} [", marker),
+    )?;
+    colorful_print(
+        Styles::with_bold(theme().background, key_color),
+        key.to_string(),
+    )?;
+    colorful_print(
+        Styles::new(theme().background, theme().option),
+        "]: ".to_string(),
+    )?;
+    colorful_print(
+        Styles::new(theme().background, desc_color),
+        format!("{}\n", desc),
+    )
 }
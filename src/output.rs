@@ -1,28 +1,27 @@
-use crate::input::disable_raw_input;
+use crate::{input::disable_raw_input, theme::Theme};
 use anyhow::Result;
 use crossterm::style::{
     Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
 };
 use crossterm::ExecutableCommand;
-use lazy_static::lazy_static;
+use serde_json::{json, Map, Value};
+use std::cell::Cell;
 use std::io::stdout;
 use std::process::Output;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
 
-lazy_static! {
-    pub static ref PROMPT_BG_COLOR: Color = hex_to_color("#222831");
-    pub static ref CODE_BG_COLOR: Color = hex_to_color("#F9E8C9");
-    pub static ref CODE_BORDER_FG_COLOR: Color = hex_to_color("#898121");
-    pub static ref CODE_FG_COLOR: Color = hex_to_color("#0A6847");
-    pub static ref COMMAND_NOTICE_FG_COLOR: Color = hex_to_color("#009FBD");
-    pub static ref COMMAND_FG_COLOR: Color = hex_to_color("#ACD793");
-    pub static ref PROMPT_FG_COLOR: Color = hex_to_color("#ECB159");
-    pub static ref PROMPT_OPTIONI_KEY_FG_COLOR: Color = hex_to_color("#CBFFA9");
-    pub static ref PROMPT_OPTIONI_QUITKEY_FG_COLOR: Color = hex_to_color("#FF6868");
-    pub static ref PROMPT_OPTIONI_DESC_FG_COLOR: Color = hex_to_color("#5BBCFF");
-    pub static ref PROMPT_ERR_FG_COLOR: Color = hex_to_color("#FF0000");
-    pub static ref PROMPT_SUCCESS_FG_COLOR: Color = hex_to_color("#CDE990");
-    pub static ref PROMPT_NOTICE_FG_COLOR: Color = hex_to_color("#C780FA");
-    pub static ref OUTTER_OUTPUT_FG_COLOR: Color = hex_to_color("#5356FF");
+thread_local! {
+    static THEME: Cell<Theme> = const { Cell::new(Theme::DARK) };
+}
+
+/// Colors what is printed from now on.
+pub fn set_theme(theme: Theme) {
+    THEME.with(|current| current.set(theme));
+}
+
+pub fn theme() -> Theme {
+    THEME.with(Cell::get)
 }
 
 pub struct Styles {
@@ -32,27 +31,107 @@ pub struct Styles {
 }
 
 impl Styles {
-    pub fn new(bg: Color, fg: Color) -> Styles {
-        Styles {
-            bg: Some(bg),
-            fg: Some(fg),
-            bold: None,
-        }
+    pub fn new(bg: Option<Color>, fg: Option<Color>) -> Styles {
+        Styles { bg, fg, bold: None }
     }
 
-    pub fn with_bold(bg: Color, fg: Color) -> Styles {
+    pub fn with_bold(bg: Option<Color>, fg: Option<Color>) -> Styles {
         Styles {
-            bg: Some(bg),
-            fg: Some(fg),
+            bg,
+            fg,
             bold: Some(true),
         }
     }
 }
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+static JSON: AtomicBool = AtomicBool::new(false);
+static STYLED: AtomicBool = AtomicBool::new(true);
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// How much of the git commands gitflow runs is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// No commands at all.
+    Quiet,
+    /// The commands that change the repository.
+    Normal,
+    /// Every command, queries included.
+    Verbose,
+    /// Every command with how long it took, after the environment gitflow runs in.
+    Debug,
+}
+
+/// Whether a git command only reads the repository or changes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandKind {
+    Query,
+    Change,
+}
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Debug,
+    }
+}
+
+/// Silences every colorful output, e.g. while printing machine readable results.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Replaces the colorful output with the JSON events of [`emit`].
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Prints without colors or bold text when `styled` is false, e.g. when stdout isn't a
+/// terminal.
+pub fn set_styled(styled: bool) {
+    STYLED.store(styled, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints `{"event": event, ...fields}` on one line when the output is JSON.
+pub fn emit(event: &str, fields: Value) -> Result<()> {
+    if !is_json() {
+        return Ok(());
+    }
+    disable_raw_input()?;
+    let mut object = Map::new();
+    object.insert("event".to_string(), event.into());
+    if let Value::Object(fields) = fields {
+        object.extend(fields);
+    }
+    println!("{}", Value::Object(object));
+    Ok(())
+}
+
+fn emit_message(level: &str, text: &str) -> Result<()> {
+    emit("message", json!({"level": level, "text": text.trim()}))
+}
+
 pub fn colorful_print(colors: Styles, content: String) -> Result<()> {
+    if QUIET.load(Ordering::Relaxed) || is_json() {
+        return Ok(());
+    }
     disable_raw_input()?;
 
     let mut o = stdout();
+    if !STYLED.load(Ordering::Relaxed) {
+        o.execute(Print(content))?;
+        return Ok(());
+    }
     if let Some(bg) = colors.bg {
         o.execute(SetBackgroundColor(bg))?;
     }
@@ -67,72 +146,89 @@ pub fn colorful_print(colors: Styles, content: String) -> Result<()> {
     Ok(())
 }
 
-fn hex_to_color(hex: &str) -> crossterm::style::Color {
-    let r = u8::from_str_radix(&hex[1..3], 16).unwrap();
-    let g = u8::from_str_radix(&hex[3..5], 16).unwrap();
-    let b = u8::from_str_radix(&hex[5..7], 16).unwrap();
-
-    crossterm::style::Color::Rgb { r, g, b }
-}
-
-pub fn output_invalid_type() -> Result<()> {
-    disable_raw_input()?;
-    colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_ERR_FG_COLOR),
-        "Invalid input. Please try again.\n".to_string(),
-    )
-}
-
 pub fn output_error(msg: &str) -> Result<()> {
+    emit_message("error", msg)?;
     disable_raw_input()?;
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_ERR_FG_COLOR),
+        Styles::new(theme().background, theme().error),
         msg.to_string(),
     )
 }
 
 pub fn output_success(msg: &str) -> Result<()> {
+    emit_message("success", msg)?;
     disable_raw_input()?;
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_SUCCESS_FG_COLOR),
+        Styles::new(theme().background, theme().success),
         msg.to_string(),
     )
 }
 
-pub fn output_success_result(result: &str) -> Result<()> {
-    colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_SUCCESS_FG_COLOR),
-        result.to_string(),
-    )
-}
-
 pub fn output_notice(notice: &str) -> Result<()> {
+    if !notice.trim().is_empty() {
+        emit_message("notice", notice)?;
+    }
     colorful_print(
-        Styles::new(*PROMPT_BG_COLOR, *PROMPT_NOTICE_FG_COLOR),
+        Styles::new(theme().background, theme().notice),
         format!("\n{}", notice).to_string(),
     )
 }
 
-pub fn command_output(command: Option<&str>, output: Output) -> Result<()> {
-    if let Some(command) = command {
-        colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *COMMAND_NOTICE_FG_COLOR),
-            "\n==> Executing: ".to_string(),
-        )?;
+/// Shows a git command that ran and what it printed, if the verbosity shows commands of its
+/// `kind`.
+pub fn command_output(
+    command: &str,
+    kind: CommandKind,
+    elapsed: Duration,
+    output: Output,
+) -> Result<()> {
+    if is_json() {
+        return emit(
+            "command",
+            json!({
+                "command": command,
+                "kind": match kind {
+                    CommandKind::Query => "query",
+                    CommandKind::Change => "change",
+                },
+                "exit_code": output.status.code(),
+                "duration_ms": elapsed.as_millis() as u64,
+                "stdout": String::from_utf8_lossy(&output.stdout),
+                "stderr": String::from_utf8_lossy(&output.stderr),
+            }),
+        );
+    }
+    let shown = match kind {
+        CommandKind::Query => Verbosity::Verbose,
+        CommandKind::Change => Verbosity::Normal,
+    };
+    if verbosity() < shown {
+        return Ok(());
+    }
+
+    colorful_print(
+        Styles::new(theme().background, theme().command_notice),
+        "\n==> Executing: ".to_string(),
+    )?;
+    colorful_print(
+        Styles::new(theme().background, theme().command),
+        format!("{}\n", command),
+    )?;
+    if verbosity() >= Verbosity::Debug {
         colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *COMMAND_FG_COLOR),
-            format!("{}\n", command),
+            Styles::new(theme().background, theme().command_notice),
+            format!("Took {} ms.\n", elapsed.as_millis()),
         )?;
     }
 
     if output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
         colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *PROMPT_NOTICE_FG_COLOR),
+            Styles::new(theme().background, theme().notice),
             "\nCommand executed successfully. Output:\n".to_string(),
         )?;
         colorful_print(
-            Styles::new(*PROMPT_BG_COLOR, *OUTTER_OUTPUT_FG_COLOR),
+            Styles::new(theme().background, theme().output),
             stdout.to_string(),
         )
     } else {
@@ -140,3 +236,48 @@ pub fn command_output(command: Option<&str>, output: Output) -> Result<()> {
         output_error(&stderr)
     }
 }
+
+/// Shows a command that would have changed the repository in a dry run.
+pub fn dry_run_output(command: &str) -> Result<()> {
+    if is_json() {
+        return emit(
+            "command",
+            json!({"command": command, "kind": "change", "dry_run": true}),
+        );
+    }
+    if verbosity() == Verbosity::Quiet {
+        return Ok(());
+    }
+    colorful_print(
+        Styles::new(theme().background, theme().command_notice),
+        "\n==> Would run: ".to_string(),
+    )?;
+    colorful_print(
+        Styles::new(theme().background, theme().command),
+        format!("{}\n", command),
+    )
+}
+
+/// Shows the directory gitflow runs in and the `GIT_*` variables that change what git does.
+pub fn output_environment() -> Result<()> {
+    if verbosity() < Verbosity::Debug {
+        return Ok(());
+    }
+    let mut lines = vec![format!(
+        "gitflow {} in {}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::current_dir()?.display()
+    )];
+    let mut vars: Vec<_> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("GIT_"))
+        .collect();
+    vars.sort();
+    lines.extend(
+        vars.iter()
+            .map(|(name, value)| format!("{}={}", name, value)),
+    );
+    colorful_print(
+        Styles::new(theme().background, theme().command_notice),
+        format!("{}\n", lines.join("\n")),
+    )
+}
@@ -1,16 +1,96 @@
-use anyhow::Result;
+use std::{fmt, path::Path};
 
-use crate::git::{git_status_short, has_commit_to_push};
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
 
-#[derive(Clone, Debug)]
+use crate::{
+    backend::GitBackend,
+    git::{check_in_git_repo, git_dir, git_status_porcelain},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct GitRemoteBranch {
     pub remote: String,
     pub branch: String,
 }
 
+/// A branch with its last commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchInfo {
+    pub name: String,
+    /// The abbreviated id of the last commit.
+    pub commit: String,
+    pub subject: String,
+    /// The committer date of the last commit, in seconds since the epoch.
+    pub committed_at: i64,
+}
+
+/// A multi-step operation that git has paused, waiting for the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+    Am,
+}
+
+impl GitOperation {
+    /// Finds the operation in progress from the state files git leaves in the git dir.
+    pub fn in_progress() -> Result<Option<Self>> {
+        Ok(Self::in_git_dir(&git_dir()?))
+    }
+
+    pub fn in_git_dir(dir: &Path) -> Option<Self> {
+        if dir.join("rebase-merge").exists() {
+            Some(Self::Rebase)
+        } else if dir.join("rebase-apply").exists() {
+            // `git am` and `git rebase --apply` share the same directory
+            if dir.join("rebase-apply").join("applying").exists() {
+                Some(Self::Am)
+            } else {
+                Some(Self::Rebase)
+            }
+        } else if dir.join("MERGE_HEAD").exists() {
+            Some(Self::Merge)
+        } else if dir.join("CHERRY_PICK_HEAD").exists() {
+            Some(Self::CherryPick)
+        } else if dir.join("REVERT_HEAD").exists() {
+            Some(Self::Revert)
+        } else if dir.join("BISECT_LOG").exists() {
+            Some(Self::Bisect)
+        } else {
+            None
+        }
+    }
+
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+            Self::Bisect => "bisect",
+            Self::Am => "am",
+        }
+    }
+
+    pub fn can_skip(&self) -> bool {
+        !matches!(self, Self::Merge)
+    }
+}
+
+impl fmt::Display for GitOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.command())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GitStatus {
-    // Uninitialized,
-    // Initialized,
+    Uninitialized,
+    Initialized,
     Clean,
     Unstaged,
     PartiallyStaged,
@@ -20,54 +100,359 @@ pub enum GitStatus {
     MessFullyCommited,
     FullyCommited,
 
+    UpToDate,
+    Behind,
+    Diverged,
+
     Conflicted,
+
+    Merging,
+    Rebasing,
+    CherryPicking,
+    Reverting,
+    Bisecting,
+    Applying,
 }
 
 impl GitStatus {
-    pub fn of(remote_branch: Option<GitRemoteBranch>) -> Result<Self> {
-        let output = git_status_short()?; // Assume this function runs `git status -s` and returns the output
+    pub fn of(snapshot: Option<&RepoSnapshot>) -> Self {
+        let Some(snapshot) = snapshot else {
+            return Self::Uninitialized;
+        };
+        if snapshot.oid.is_none() {
+            return Self::Initialized;
+        }
+        if let Some(operation) = snapshot.operation {
+            return Self::from(operation);
+        }
+        if snapshot.has_conflicts() {
+            return Self::Conflicted;
+        }
+
+        let tracked = snapshot.upstream.is_some();
+        let has_local_commit_to_push = tracked && snapshot.ahead > 0;
+        match (
+            has_local_commit_to_push,
+            snapshot.has_staged(),
+            snapshot.has_unstaged(),
+        ) {
+            (_, false, false) if tracked && snapshot.behind > 0 => {
+                if has_local_commit_to_push {
+                    Self::Diverged
+                } else {
+                    Self::Behind
+                }
+            }
+            (false, false, false) if tracked => Self::UpToDate,
+            (false, false, false) => Self::Clean,
+            (false, false, true) => Self::Unstaged,
+            (false, true, false) => Self::FullyStaged,
+            (false, true, true) => Self::PartiallyStaged,
+            (true, false, false) => Self::FullyCommited,
+            (true, false, true) => Self::PartiallyCommited,
+            (true, true, false) => Self::MessFullyCommited,
+            (true, true, true) => Self::MessPartiallyCommited,
+        }
+    }
+}
 
-        let lines: Vec<&str> = output.lines().collect();
+impl From<GitOperation> for GitStatus {
+    fn from(operation: GitOperation) -> Self {
+        match operation {
+            GitOperation::Merge => Self::Merging,
+            GitOperation::Rebase => Self::Rebasing,
+            GitOperation::CherryPick => Self::CherryPicking,
+            GitOperation::Revert => Self::Reverting,
+            GitOperation::Bisect => Self::Bisecting,
+            GitOperation::Am => Self::Applying,
+        }
+    }
+}
 
-        let mut staged = false;
-        let mut unstaged = false;
-        let mut need_resolve = false;
-        let mut has_local_commit_to_push = false;
+/// The state of a file on one side (index or worktree) of a status entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileState {
+    Unmodified,
+    Modified,
+    TypeChanged,
+    Added,
+    Deleted,
+    Renamed,
+    Copied,
+    Unmerged,
+    Untracked,
+    Ignored,
+}
 
-        for line in lines {
-            let (first, second) = (&line[0..1], &line[1..2]);
-            match first {
-                "M" | "A" | "D" | "R" | "C" | "T" => staged = true,
-                _ => {}
-            }
-            match second {
-                "M" | "D" | "?" | "T" | "R" | "C" | "!" => unstaged = true,
-                _ => {}
-            }
-            match (first, second) {
-                ("U", "U") | ("A", "A") => need_resolve = true,
-                _ => {}
-            }
+impl FileState {
+    fn parse(c: char) -> Result<Self> {
+        Ok(match c {
+            '.' => Self::Unmodified,
+            'M' => Self::Modified,
+            'T' => Self::TypeChanged,
+            'A' => Self::Added,
+            'D' => Self::Deleted,
+            'R' => Self::Renamed,
+            'C' => Self::Copied,
+            'U' => Self::Unmerged,
+            _ => bail!("Unknown file state: {}", c),
+        })
+    }
+
+    pub fn is_change(&self) -> bool {
+        !matches!(self, Self::Unmodified | Self::Ignored)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::Unmodified => "unmodified",
+            Self::Modified => "modified",
+            Self::TypeChanged => "typechange",
+            Self::Added => "new file",
+            Self::Deleted => "deleted",
+            Self::Renamed => "renamed",
+            Self::Copied => "copied",
+            Self::Unmerged => "unmerged",
+            Self::Untracked => "untracked",
+            Self::Ignored => "ignored",
         }
+    }
+}
 
-        if let Some(remote_branch) = remote_branch {
-            has_local_commit_to_push =
-                has_commit_to_push(remote_branch.remote, remote_branch.branch)?;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubmoduleState {
+    pub commit_changed: bool,
+    pub modified: bool,
+    pub untracked: bool,
+}
+
+impl SubmoduleState {
+    /// Parses the `<sub>` field: `N...` for a regular file, `S<c><m><u>` for a submodule.
+    fn parse(field: &str) -> Result<Option<Self>> {
+        let flags: Vec<char> = field.chars().collect();
+        match flags.as_slice() {
+            ['N', ..] => Ok(None),
+            ['S', c, m, u] => Ok(Some(Self {
+                commit_changed: *c == 'C',
+                modified: *m == 'M',
+                untracked: *u == 'U',
+            })),
+            _ => bail!("Invalid submodule field: {}", field),
         }
+    }
+}
 
-        if need_resolve {
-            Ok(Self::Conflicted)
-        } else {
-            match (has_local_commit_to_push, staged, unstaged) {
-                (false, false, false) => Ok(Self::Clean),
-                (false, false, true) => Ok(Self::Unstaged),
-                (false, true, false) => Ok(Self::FullyStaged),
-                (false, true, true) => Ok(Self::PartiallyStaged),
-                (true, false, false) => Ok(Self::FullyCommited),
-                (true, false, true) => Ok(Self::PartiallyCommited),
-                (true, true, false) => Ok(Self::MessFullyCommited),
-                (true, true, true) => Ok(Self::MessPartiallyCommited),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusEntry {
+    pub path: String,
+    /// The source path of a rename or copy.
+    pub orig_path: Option<String>,
+    pub index: FileState,
+    pub worktree: FileState,
+    pub conflicted: bool,
+    pub submodule: Option<SubmoduleState>,
+}
+
+impl StatusEntry {
+    pub fn is_staged(&self) -> bool {
+        !self.conflicted && self.index.is_change()
+    }
+
+    pub fn is_unstaged(&self) -> bool {
+        !self.conflicted && self.worktree.is_change()
+    }
+}
+
+/// Everything the flow needs to know about the repository, read from one
+/// `git status --porcelain=v2 --branch -z` call.
+#[derive(Clone, Debug, Default)]
+pub struct RepoSnapshot {
+    /// The commit HEAD points to, `None` before the first commit.
+    pub oid: Option<String>,
+    /// The current branch, `None` when HEAD is detached.
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub entries: Vec<StatusEntry>,
+    pub operation: Option<GitOperation>,
+}
+
+impl RepoSnapshot {
+    /// Reads the current repository, `None` when not inside a git work tree.
+    pub fn capture() -> Result<Option<Self>> {
+        if !check_in_git_repo()? {
+            return Ok(None);
+        }
+        let mut snapshot = Self::parse(&git_status_porcelain()?)?;
+        snapshot.operation = GitOperation::in_progress()?;
+        Ok(Some(snapshot))
+    }
+
+    pub fn parse(output: &str) -> Result<Self> {
+        let mut snapshot = Self::default();
+        let mut records = output.split('\0').filter(|record| !record.is_empty());
+        while let Some(record) = records.next() {
+            let (kind, rest) = record.split_once(' ').unwrap_or((record, ""));
+            match kind {
+                "#" => snapshot.parse_header(rest)?,
+                "1" => {
+                    let fields: Vec<&str> = rest.splitn(8, ' ').collect();
+                    let [xy, sub, _, _, _, _, _, path] = fields[..] else {
+                        bail!("Invalid status record: {}", record);
+                    };
+                    snapshot
+                        .entries
+                        .push(Self::changed_entry(xy, sub, path, None, false)?);
+                }
+                "2" => {
+                    let fields: Vec<&str> = rest.splitn(9, ' ').collect();
+                    let [xy, sub, _, _, _, _, _, _, path] = fields[..] else {
+                        bail!("Invalid status record: {}", record);
+                    };
+                    let orig_path = records
+                        .next()
+                        .context(format!("Missing original path of {}", path))?;
+                    snapshot.entries.push(Self::changed_entry(
+                        xy,
+                        sub,
+                        path,
+                        Some(orig_path),
+                        false,
+                    )?);
+                }
+                "u" => {
+                    let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                    let [xy, sub, _, _, _, _, _, _, _, path] = fields[..] else {
+                        bail!("Invalid status record: {}", record);
+                    };
+                    snapshot
+                        .entries
+                        .push(Self::changed_entry(xy, sub, path, None, true)?);
+                }
+                "?" | "!" => snapshot.entries.push(StatusEntry {
+                    path: rest.to_string(),
+                    orig_path: None,
+                    index: FileState::Unmodified,
+                    worktree: if kind == "?" {
+                        FileState::Untracked
+                    } else {
+                        FileState::Ignored
+                    },
+                    conflicted: false,
+                    submodule: None,
+                }),
+                _ => bail!("Invalid status record: {}", record),
             }
         }
+        Ok(snapshot)
     }
+
+    fn parse_header(&mut self, header: &str) -> Result<()> {
+        let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+        match key {
+            "branch.oid" => self.oid = (value != "(initial)").then(|| value.to_string()),
+            "branch.head" => self.branch = (value != "(detached)").then(|| value.to_string()),
+            "branch.upstream" => self.upstream = Some(value.to_string()),
+            "branch.ab" => {
+                let (ahead, behind) = value
+                    .split_once(' ')
+                    .context(format!("Invalid branch.ab header: {}", value))?;
+                self.ahead = ahead.trim_start_matches('+').parse()?;
+                self.behind = behind.trim_start_matches('-').parse()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn changed_entry(
+        xy: &str,
+        sub: &str,
+        path: &str,
+        orig_path: Option<&str>,
+        conflicted: bool,
+    ) -> Result<StatusEntry> {
+        let mut states = xy.chars();
+        let (Some(index), Some(worktree), None) = (states.next(), states.next(), states.next())
+        else {
+            bail!("Invalid XY field: {}", xy);
+        };
+        Ok(StatusEntry {
+            path: path.to_string(),
+            orig_path: orig_path.map(String::from),
+            index: FileState::parse(index)?,
+            worktree: FileState::parse(worktree)?,
+            conflicted,
+            submodule: SubmoduleState::parse(sub)?,
+        })
+    }
+
+    /// Compares HEAD with the remote branch the user chose to track, which may differ from
+    /// the configured upstream. `None` means nothing is tracked.
+    pub fn track(
+        &mut self,
+        git: &dyn GitBackend,
+        remote_branch: Option<&GitRemoteBranch>,
+    ) -> Result<()> {
+        let tracked = remote_branch.map(|rb| format!("{}/{}", rb.remote, rb.branch));
+        if tracked != self.upstream {
+            (self.ahead, self.behind) = match &tracked {
+                Some(tracked) => git.ahead_behind(tracked)?,
+                None => (0, 0),
+            };
+            self.upstream = tracked;
+        }
+        Ok(())
+    }
+
+    pub fn upstream_branch(&self) -> Option<GitRemoteBranch> {
+        let (remote, branch) = self.upstream.as_ref()?.split_once('/')?;
+        Some(GitRemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+        })
+    }
+
+    pub fn has_staged(&self) -> bool {
+        self.entries.iter().any(StatusEntry::is_staged)
+    }
+
+    pub fn has_unstaged(&self) -> bool {
+        self.entries.iter().any(StatusEntry::is_unstaged)
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        self.entries.iter().any(|entry| entry.conflicted)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "oid": self.oid,
+            "branch": self.branch,
+            "upstream": self.upstream,
+            "ahead": self.ahead,
+            "behind": self.behind,
+            "operation": self.operation.map(|operation| operation.command()),
+            "files": self
+                .entries
+                .iter()
+                .map(|entry| json!({
+                    "path": entry.path,
+                    "orig_path": entry.orig_path,
+                    "index": format!("{:?}", entry.index),
+                    "worktree": format!("{:?}", entry.worktree),
+                    "conflicted": entry.conflicted,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// The status and repository as one JSON object.
+pub fn status_json(snapshot: Option<&RepoSnapshot>) -> Value {
+    json!({
+        "status": format!("{:?}", GitStatus::of(snapshot)),
+        "repository": snapshot.map(RepoSnapshot::to_json),
+    })
 }
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::{
+    backend::GitBackend,
+    flow,
+    output::{colorful_print, emit, is_json, set_quiet, theme, Styles},
+    status::{status_json, FileState, GitStatus, RepoSnapshot, StatusEntry},
+};
+
+/// A one-screen overview of the repository, printed by `gitflow status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// The branch, upstream, status, operation and stash lines.
+    pub header: Vec<String>,
+    /// Titled groups of lines, only the ones with something in them.
+    pub sections: Vec<(String, Vec<String>)>,
+}
+
+/// Prints the summary of the repository without asking anything, as a `summary` event with
+/// JSON output.
+pub fn show(git: &dyn GitBackend) -> Result<()> {
+    // the git commands run to collect it would get in the way
+    set_quiet(true);
+    let facts = gather(git);
+    set_quiet(false);
+    let facts = facts?;
+    if is_json() {
+        let mut event = status_json(facts.as_ref().map(|(snapshot, _, _)| snapshot));
+        if let Some((_, stashes, diff_stat)) = facts {
+            event["stashes"] = stashes.into();
+            event["diff_stat"] = diff_stat.into();
+        }
+        return emit("summary", event);
+    }
+    Summary::from_facts(facts).print()
+}
+
+/// The snapshot, stash count and diff stat against the upstream, `None` outside a repository.
+fn gather(git: &dyn GitBackend) -> Result<Option<(RepoSnapshot, usize, Option<String>)>> {
+    let Some(snapshot) = flow::capture(git)? else {
+        return Ok(None);
+    };
+    let diff_stat = match snapshot.upstream_branch() {
+        Some(upstream) if snapshot.oid.is_some() => {
+            Some(git.diff_stat(&upstream.remote, &upstream.branch)?)
+        }
+        _ => None,
+    };
+    let stashes = git.stash_count()?;
+    Ok(Some((snapshot, stashes, diff_stat)))
+}
+
+impl Summary {
+    pub fn collect(git: &dyn GitBackend) -> Result<Self> {
+        Ok(Self::from_facts(gather(git)?))
+    }
+
+    fn from_facts(facts: Option<(RepoSnapshot, usize, Option<String>)>) -> Self {
+        match facts {
+            Some((snapshot, stashes, diff_stat)) => {
+                Self::of(&snapshot, stashes, diff_stat.as_deref())
+            }
+            None => Self {
+                header: vec!["Not a git repository.".to_string()],
+                sections: vec![],
+            },
+        }
+    }
+
+    pub fn of(snapshot: &RepoSnapshot, stashes: usize, diff_stat: Option<&str>) -> Self {
+        let mut header = vec![match (&snapshot.branch, &snapshot.oid) {
+            (Some(branch), Some(_)) => format!("On branch {}", branch),
+            (Some(branch), None) => format!("On branch {}, no commits yet", branch),
+            (None, Some(oid)) => format!("HEAD detached at {}", &oid[..oid.len().min(7)]),
+            (None, None) => "HEAD detached".to_string(),
+        }];
+        header.push(match &snapshot.upstream {
+            Some(upstream) => format!(
+                "Upstream: {}, {} ahead, {} behind",
+                upstream, snapshot.ahead, snapshot.behind
+            ),
+            None => "Upstream: none".to_string(),
+        });
+        header.push(format!("Status: {:?}", GitStatus::of(Some(snapshot))));
+        if let Some(operation) = snapshot.operation {
+            header.push(format!("In progress: {}", operation.command()));
+        }
+        header.push(format!("Stashes: {}", stashes));
+
+        let entries = &snapshot.entries;
+        let untracked = |entry: &&StatusEntry| entry.worktree == FileState::Untracked;
+        let sections: [(&str, Vec<String>); 4] = [
+            (
+                "Staged",
+                entries
+                    .iter()
+                    .filter(|entry| entry.is_staged())
+                    .map(|entry| file_line(entry.index, entry))
+                    .collect(),
+            ),
+            (
+                "Unstaged",
+                entries
+                    .iter()
+                    .filter(|entry| entry.is_unstaged() && !untracked(entry))
+                    .map(|entry| file_line(entry.worktree, entry))
+                    .collect(),
+            ),
+            (
+                "Untracked",
+                entries
+                    .iter()
+                    .filter(untracked)
+                    .map(|entry| entry.path.clone())
+                    .collect(),
+            ),
+            (
+                "Conflicted",
+                entries
+                    .iter()
+                    .filter(|entry| entry.conflicted)
+                    .map(|entry| entry.path.clone())
+                    .collect(),
+            ),
+        ];
+        let mut sections: Vec<(String, Vec<String>)> = sections
+            .into_iter()
+            .filter(|(_, lines)| !lines.is_empty())
+            .map(|(title, lines)| (format!("{} ({})", title, lines.len()), lines))
+            .collect();
+        if let (Some(upstream), Some(diff_stat)) = (&snapshot.upstream, diff_stat) {
+            if !diff_stat.trim().is_empty() {
+                sections.push((
+                    format!("Changes against {}", upstream),
+                    diff_stat
+                        .lines()
+                        .map(|line| line.trim().to_string())
+                        .collect(),
+                ));
+            }
+        }
+        Self { header, sections }
+    }
+
+    pub fn print(&self) -> Result<()> {
+        for line in &self.header {
+            colorful_print(
+                Styles::new(theme().background, theme().prompt),
+                format!("{}\n", line),
+            )?;
+        }
+        for (title, lines) in &self.sections {
+            colorful_print(
+                Styles::with_bold(theme().background, theme().notice),
+                format!("\n{}:\n", title),
+            )?;
+            for line in lines {
+                colorful_print(
+                    Styles::new(theme().background, theme().output),
+                    format!("  {}\n", line),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_line(state: FileState, entry: &StatusEntry) -> String {
+    let path = match &entry.orig_path {
+        Some(orig_path) => format!("{} -> {}", orig_path, entry.path),
+        None => entry.path.clone(),
+    };
+    format!("{:<12}{}", format!("{}:", state.describe()), path)
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.header {
+            writeln!(f, "{}", line)?;
+        }
+        for (title, lines) in &self.sections {
+            writeln!(f, "\n{}:", title)?;
+            for line in lines {
+                writeln!(f, "  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
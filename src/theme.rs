@@ -0,0 +1,206 @@
+use std::{collections::BTreeMap, env};
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Color;
+use serde::Deserialize;
+
+use crate::config::Config;
+
+/// The colors of everything gitflow prints, `None` for the terminal's own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub background: Option<Color>,
+    pub code_background: Option<Color>,
+    pub code_border: Option<Color>,
+    pub code: Option<Color>,
+    pub command_notice: Option<Color>,
+    pub command: Option<Color>,
+    pub prompt: Option<Color>,
+    pub option_key: Option<Color>,
+    pub quit_key: Option<Color>,
+    pub option: Option<Color>,
+    pub error: Option<Color>,
+    pub success: Option<Color>,
+    pub notice: Option<Color>,
+    /// What git commands print.
+    pub output: Option<Color>,
+}
+
+/// A theme defined in the configuration, as `[themes.<name>]`: `base` names the built-in
+/// theme it starts from, every other key overrides a color with `#rrggbb`, or `none` for
+/// the terminal's own.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct Palette {
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub colors: BTreeMap<String, String>,
+}
+
+const fn rgb(hex: u32) -> Option<Color> {
+    Some(Color::Rgb {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+    })
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        background: rgb(0x222831),
+        code_background: rgb(0xF9E8C9),
+        code_border: rgb(0x898121),
+        code: rgb(0x0A6847),
+        command_notice: rgb(0x009FBD),
+        command: rgb(0xACD793),
+        prompt: rgb(0xECB159),
+        option_key: rgb(0xCBFFA9),
+        quit_key: rgb(0xFF6868),
+        option: rgb(0x5BBCFF),
+        error: rgb(0xFF0000),
+        success: rgb(0xCDE990),
+        notice: rgb(0xC780FA),
+        output: rgb(0x5356FF),
+    };
+
+    /// Dark text on the terminal's own background.
+    pub const LIGHT: Theme = Theme {
+        background: None,
+        code_background: rgb(0xEDE6D6),
+        code_border: rgb(0x8A7F1E),
+        code: rgb(0x0A6847),
+        command_notice: rgb(0x00738A),
+        command: rgb(0x3B7A1E),
+        prompt: rgb(0x9A5B00),
+        option_key: rgb(0x2E7D32),
+        quit_key: rgb(0xC62828),
+        option: rgb(0x1565C0),
+        error: rgb(0xD50000),
+        success: rgb(0x2E7D32),
+        notice: rgb(0x7B1FA2),
+        output: rgb(0x283593),
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        background: rgb(0x000000),
+        code_background: rgb(0xFFFFFF),
+        code_border: rgb(0xFFFF00),
+        code: rgb(0x000000),
+        command_notice: rgb(0x00FFFF),
+        command: rgb(0x00FF00),
+        prompt: rgb(0xFFFF00),
+        option_key: rgb(0x00FF00),
+        quit_key: rgb(0xFF5555),
+        option: rgb(0xFFFFFF),
+        error: rgb(0xFF0000),
+        success: rgb(0x00FF00),
+        notice: rgb(0xFF00FF),
+        output: rgb(0xFFFFFF),
+    };
+
+    /// No colors, only bold text.
+    pub const MONOCHROME: Theme = Theme {
+        background: None,
+        code_background: None,
+        code_border: None,
+        code: None,
+        command_notice: None,
+        command: None,
+        prompt: None,
+        option_key: None,
+        quit_key: None,
+        option: None,
+        error: None,
+        success: None,
+        notice: None,
+        output: None,
+    };
+
+    pub const NAMES: [&'static str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+    /// The built-in theme called `name`.
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            "high-contrast" => Some(Self::HIGH_CONTRAST),
+            "monochrome" => Some(Self::MONOCHROME),
+            _ => None,
+        }
+    }
+
+    /// The theme `config` selects, a built-in one or one of its palettes, dark by default.
+    pub fn from_config(config: &Config) -> Result<Theme> {
+        let name = config.theme.as_deref().unwrap_or("dark");
+        let Some(palette) = config.themes.get(name) else {
+            return Self::named(name).with_context(|| {
+                format!(
+                    "Unknown theme: {}, expected one of {} or a [themes.{}] table",
+                    name,
+                    Self::NAMES.join(", "),
+                    name
+                )
+            });
+        };
+        let base = palette.base.as_deref().unwrap_or("dark");
+        let mut theme = Self::named(base).with_context(|| {
+            format!(
+                "Unknown base of theme {}: {}, expected one of {}",
+                name,
+                base,
+                Self::NAMES.join(", ")
+            )
+        })?;
+        for (role, value) in &palette.colors {
+            let color = parse_color(value)
+                .with_context(|| format!("Invalid {} of theme {}", role, name))?;
+            *theme
+                .role_mut(role)
+                .with_context(|| format!("Unknown color of theme {}: {}", name, role))? = color;
+        }
+        Ok(theme)
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Option<Color>> {
+        Some(match role {
+            "background" => &mut self.background,
+            "code-background" => &mut self.code_background,
+            "code-border" => &mut self.code_border,
+            "code" => &mut self.code,
+            "command-notice" => &mut self.command_notice,
+            "command" => &mut self.command,
+            "prompt" => &mut self.prompt,
+            "option-key" => &mut self.option_key,
+            "quit-key" => &mut self.quit_key,
+            "option" => &mut self.option,
+            "error" => &mut self.error,
+            "success" => &mut self.success,
+            "notice" => &mut self.notice,
+            "output" => &mut self.output,
+            _ => return None,
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+/// `#rrggbb`, or `none` for the terminal's own color.
+fn parse_color(value: &str) -> Result<Option<Color>> {
+    if value == "none" {
+        return Ok(None);
+    }
+    match value.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16)
+            .map(rgb)
+            .with_context(|| format!("{} is not a color", value)),
+        _ => bail!("{} is not a color, expected #rrggbb or none", value),
+    }
+}
+
+/// Whether `NO_COLOR` asks for output without colors, see <https://no-color.org>.
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::TestRepo;
+use gitflow::{
+    backend::{CliBackend, GitBackend, MemoryBackend, MemoryRepo},
+    git::GitError,
+};
+
+fn branch_names(git: &dyn GitBackend, remote: Option<&str>) -> Vec<String> {
+    let branches = git.branch_details(remote).unwrap();
+    branches.into_iter().map(|branch| branch.name).collect()
+}
+
+fn with_dated_branches() -> TestRepo {
+    let repo = TestRepo::with_origin();
+    for (branch, timestamp) in [("old", 1_000_000_000), ("new", 1_700_000_000)] {
+        repo.git(&["checkout", "-q", "-b", branch, "main"]);
+        repo.commit_file_at(&format!("{}.txt", branch), "x\n", timestamp);
+        repo.git(&["push", "-q", "origin", branch]);
+    }
+    repo.git(&["checkout", "-q", "main"]);
+    repo
+}
+
+#[test]
+fn branch_details_most_recent_first() {
+    let repo = with_dated_branches();
+    assert_eq!(branch_names(&CliBackend, None), ["main", "new", "old"]);
+    // origin/HEAD is not a branch
+    repo.git(&["remote", "set-head", "origin", "main"]);
+    assert_eq!(
+        branch_names(&CliBackend, Some("origin")),
+        ["main", "new", "old"]
+    );
+
+    let details = CliBackend.branch_details(None).unwrap();
+    let old = details.iter().find(|branch| branch.name == "old").unwrap();
+    assert_eq!(old.committed_at, 1_000_000_000);
+    assert_eq!(old.subject, "update old.txt");
+    assert_eq!(old.commit, repo.git(&["rev-parse", "--short", "old"]));
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn native_branch_details_match_cli() {
+    use gitflow::backend::NativeBackend;
+
+    let repo = with_dated_branches();
+    repo.git(&["remote", "set-head", "origin", "main"]);
+    for remote in [None, Some("origin")] {
+        assert_eq!(
+            NativeBackend::default().branch_details(remote).unwrap(),
+            CliBackend.branch_details(remote).unwrap()
+        );
+    }
+}
+
+#[test]
+fn memory_branch_details() {
+    let mut repo = MemoryRepo::new("main");
+    repo.branches.push("feature".to_string());
+    let git = MemoryBackend::new(repo);
+    assert_eq!(branch_names(&git, None), ["main", "feature"]);
+}
+
+#[test]
+fn rejected_paths_fail_to_stage() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let paths = ["a.txt".to_string(), "missing.txt".to_string()];
+    let err = CliBackend.stage(&paths).unwrap_err();
+    assert!(err.downcast_ref::<GitError>().is_some());
+    assert!(err.to_string().contains("missing.txt"), "{:#}", err);
+    let err = CliBackend
+        .unstage(&[":(bad)a.txt".to_string()])
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Failed to unstage the files"));
+    assert_eq!(repo.git(&["status", "--porcelain"]), "?? a.txt");
+}
+
+#[test]
+fn failures_say_what_git_reported() {
+    let _repo = TestRepo::with_origin();
+    let err = CliBackend.checkout("missing").unwrap_err();
+    assert!(err.downcast_ref::<GitError>().is_some());
+    assert!(err.to_string().contains("'missing'"), "{:#}", err);
+    let err = CliBackend.push("nowhere", "main").unwrap_err();
+    assert!(err.to_string().starts_with("Failed to push: "), "{:#}", err);
+    assert!(err.to_string().contains("nowhere"), "{:#}", err);
+}
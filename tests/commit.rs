@@ -0,0 +1,180 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{drive, TestRepo};
+use gitflow::{
+    backend::{CliBackend, MemoryBackend, MemoryRepo},
+    git::Commit,
+    message,
+    status::{FileState, StatusEntry},
+};
+
+#[test]
+fn parses_suggested_commands() {
+    assert_eq!(
+        Commit::parse(r#"git commit -m "feat: add \"a\"" -m 'the body'"#).unwrap(),
+        Commit::new("feat: add \"a\"\n\nthe body")
+    );
+    assert_eq!(
+        Commit::parse("git commit --signoff --no-verify --amend --message=fix").unwrap(),
+        Commit {
+            amend: true,
+            signoff: true,
+            no_verify: true,
+            ..Commit::new("fix")
+        }
+    );
+    for (command, message) in [
+        ("rm -rf /", "Not a git commit command"),
+        (
+            "git commit -am 'x'",
+            "Unsupported option of git commit: -am",
+        ),
+        ("git commit -m 'x", "Unterminated quote"),
+        ("git commit --amend", "No commit message"),
+    ] {
+        let err = Commit::parse(command).unwrap_err();
+        assert!(err.to_string().starts_with(message), "{:#}", err);
+    }
+}
+
+#[test]
+fn shows_the_command_quoted() {
+    let commit = Commit {
+        signoff: true,
+        ..Commit::new("it's done")
+    };
+    assert_eq!(
+        commit.to_string(),
+        r"git commit --signoff -m 'it'\''s done'"
+    );
+    assert_eq!(commit.args(), ["commit", "--signoff", "-m", "it's done"]);
+}
+
+#[test]
+fn messages_never_reach_a_shell() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+    let message = "add $(touch pwned) `touch pwned`; touch pwned";
+    // sign it off before committing
+    drive(&CliBackend, &["c", "i", message, "s", "y"]);
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%B"]),
+        format!("{}\n\nSigned-off-by: Tester <tester@example.com>", message)
+    );
+    assert!(!repo.dir.join("pwned").exists());
+}
+
+#[test]
+fn memory_amends_without_a_new_commit() {
+    let git = MemoryBackend::new(
+        MemoryRepo::new("main")
+            .with_upstream("origin", "main", 1, 0)
+            .with_file("a.txt", FileState::Modified, FileState::Unmodified),
+    );
+    drive(&git, &["c", "i", "fix a", "m", "n", "n", "y"]);
+    let repo = git.repo();
+    assert_eq!((repo.commits, repo.ahead), (1, 1));
+    assert!(repo.entries.is_empty());
+    assert_eq!(git.commands(), ["git commit --amend -m 'fix a'"]);
+}
+
+#[test]
+fn templates_list_the_staged_files() {
+    let entry = |path: &str, orig_path: Option<&str>, index| StatusEntry {
+        path: path.to_string(),
+        orig_path: orig_path.map(String::from),
+        index,
+        worktree: FileState::Unmodified,
+        conflicted: false,
+        submodule: None,
+    };
+    let text = message::template(
+        "Subject\n\n",
+        &[
+            &entry("a.txt", None, FileState::Modified),
+            &entry("c.txt", Some("b.txt"), FileState::Renamed),
+        ],
+    );
+    assert!(text.starts_with("Subject\n\n# Please enter the commit message"));
+    assert!(text.ends_with(
+        "# Changes to be committed:\n\
+         #\tmodified:   a.txt\n\
+         #\trenamed:    b.txt -> c.txt\n"
+    ));
+    assert_eq!(message::strip(&text), "Subject");
+}
+
+#[test]
+fn templates_follow_a_message() {
+    assert_eq!(
+        message::with_template("Add a\n", "# header\nSubject\n\nWhy:\n# explain why\n"),
+        "Add a\n\n# header\n\nWhy:\n# explain why\n"
+    );
+    assert_eq!(
+        message::with_template("Add a", "# only\n"),
+        "Add a\n\n# only\n"
+    );
+}
+
+#[test]
+fn strips_comments_and_blank_lines() {
+    assert_eq!(
+        message::strip("\n# comment\nSubject  \n\n\n\nBody\n# more\nline\n\n"),
+        "Subject\n\nBody\nline"
+    );
+    assert_eq!(message::strip("# only\n\n  \n"), "");
+}
+
+/// Runs gitflow with `keys` to commit with `editor`, the repository having `a.txt` staged and
+/// a `commit.template`.
+fn commit_in_editor(keys: &str, editor: &str) -> (TestRepo, bool) {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+    let template = repo.dir.join(".git").join("template");
+    fs::write(&template, "Subject\n\nWhy:\n# explain why\n").unwrap();
+    repo.git(&["config", "commit.template", template.to_str().unwrap()]);
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["-a", "--keys", keys])
+        .env("GIT_EDITOR", editor)
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    (repo, output.status.success())
+}
+
+#[test]
+fn writes_the_message_in_the_editor() {
+    let (repo, success) = commit_in_editor(
+        "c,e,y",
+        "edit() { echo \"$1\" > .git/path; cp \"$1\" .git/seen; sed -i -e 's/^Subject$/Add a/' -e 's/^Why:$/Why: tests/' \"$1\"; }; edit",
+    );
+    assert!(success);
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%B"]),
+        "Add a\n\nWhy: tests"
+    );
+    let seen = fs::read_to_string(repo.dir.join(".git").join("seen")).unwrap();
+    assert!(seen.starts_with("Subject\n\nWhy:\n# explain why\n"));
+    assert!(seen.contains("#\tnew file:   a.txt\n"));
+    // the message is written where git writes it
+    let path = fs::read_to_string(repo.dir.join(".git").join("path")).unwrap();
+    assert_eq!(path.trim(), ".git/COMMIT_EDITMSG");
+}
+
+#[test]
+fn an_unedited_template_aborts() {
+    let (repo, success) = commit_in_editor("c,e,y", "true");
+    assert!(!success);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "A  a.txt");
+}
+
+#[test]
+fn a_typed_message_keeps_the_template() {
+    let (repo, success) = commit_in_editor("c,i,Add a,e,y", "true");
+    assert!(success);
+    assert_eq!(repo.git(&["log", "-1", "--format=%B"]), "Add a\n\nWhy:");
+}
@@ -0,0 +1,210 @@
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use clap::Parser;
+use gitflow::{
+    args::Args,
+    backend::{CliBackend, GitBackend},
+    flow,
+    input::{self, ScriptExhausted},
+    status::{GitStatus, RepoSnapshot},
+};
+
+// the flow runs git in the current directory, which is shared by all tests of a binary
+static CWD: Mutex<()> = Mutex::new(());
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "gitflow-{}-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn git_in(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Removes the escape sequences that color the output.
+pub fn strip_colors(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// A throwaway repository, the current directory while it lives.
+pub struct TestRepo {
+    pub dir: PathBuf,
+    /// A bare repository acting as `origin`.
+    pub origin: Option<PathBuf>,
+    _cwd: MutexGuard<'static, ()>,
+}
+
+impl TestRepo {
+    /// A directory that is not a git repository.
+    pub fn uninitialized() -> Self {
+        let cwd = CWD.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = temp_dir("work");
+        env::set_current_dir(&dir).unwrap();
+        Self {
+            dir,
+            origin: None,
+            _cwd: cwd,
+        }
+    }
+
+    /// A repository without any commit.
+    pub fn initialized() -> Self {
+        let repo = Self::uninitialized();
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "Tester"]);
+        repo.git(&["config", "user.email", "tester@example.com"]);
+        repo
+    }
+
+    /// A repository with one commit and no remote.
+    pub fn local() -> Self {
+        let repo = Self::initialized();
+        repo.commit_file("README.md", "hello\n");
+        repo
+    }
+
+    /// A repository with one commit pushed to `origin/main`, which is its upstream.
+    pub fn with_origin() -> Self {
+        let mut repo = Self::local();
+        let origin = repo.add_origin();
+        repo.git(&["push", "-q", "-u", "origin", "main"]);
+        repo.origin = Some(origin);
+        repo
+    }
+
+    /// Creates the bare `origin` repository and adds it as remote without pushing.
+    pub fn add_origin(&self) -> PathBuf {
+        let origin = temp_dir("origin.git");
+        git_in(&origin, &["init", "-q", "--bare", "-b", "main"]);
+        self.git(&["remote", "add", "origin", origin.to_str().unwrap()]);
+        origin
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        git_in(&self.dir, args)
+    }
+
+    /// Runs git expecting it to fail, e.g. on a conflict.
+    pub fn git_fails(&self, args: &[&str]) {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "git {} succeeded", args.join(" "));
+    }
+
+    pub fn write(&self, path: &str, content: &str) {
+        fs::write(self.dir.join(path), content).unwrap();
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.dir.join(path)).unwrap()
+    }
+
+    pub fn commit_file(&self, path: &str, content: &str) {
+        self.write(path, content);
+        self.git(&["add", path]);
+        self.git(&["commit", "-q", "-m", &format!("update {}", path)]);
+    }
+
+    /// Like [`TestRepo::commit_file`] with the committer date set to `timestamp`.
+    pub fn commit_file_at(&self, path: &str, content: &str, timestamp: i64) {
+        self.write(path, content);
+        self.git(&["add", path]);
+        let output = Command::new("git")
+            .args(["commit", "-q", "-m", &format!("update {}", path)])
+            .env("GIT_COMMITTER_DATE", format!("@{} +0000", timestamp))
+            .current_dir(&self.dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    /// Commits `content` to `origin/main` from another clone, then fetches it.
+    pub fn push_from_elsewhere(&self, path: &str, content: &str) {
+        let origin = self.origin.as_ref().expect("no origin");
+        let other = temp_dir("other");
+        git_in(&other, &["clone", "-q", origin.to_str().unwrap(), "."]);
+        git_in(&other, &["config", "user.name", "Other"]);
+        git_in(&other, &["config", "user.email", "other@example.com"]);
+        fs::write(other.join(path), content).unwrap();
+        git_in(&other, &["add", path]);
+        git_in(&other, &["commit", "-q", "-m", "remote change"]);
+        git_in(&other, &["push", "-q", "origin", "main"]);
+        fs::remove_dir_all(other).unwrap();
+        self.git(&["fetch", "-q", "origin"]);
+    }
+
+    pub fn head(&self) -> String {
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    pub fn origin_head(&self, branch: &str) -> String {
+        git_in(
+            self.origin.as_ref().expect("no origin"),
+            &["rev-parse", branch],
+        )
+    }
+
+    pub fn snapshot(&self) -> Option<RepoSnapshot> {
+        CliBackend.status().unwrap()
+    }
+
+    pub fn status(&self) -> GitStatus {
+        GitStatus::of(self.snapshot().as_ref())
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+        if let Some(origin) = &self.origin {
+            let _ = fs::remove_dir_all(origin);
+        }
+    }
+}
+
+/// Runs the interactive flow against `git`, answering prompts with `inputs` until they run out.
+pub fn drive(git: &dyn GitBackend, inputs: &[&str]) {
+    input::script(inputs);
+    let err = flow::run(git, Args::parse_from(["gitflow", "--auto-upstream"])).unwrap_err();
+    assert!(err.is::<ScriptExhausted>(), "flow failed: {:?}", err);
+}
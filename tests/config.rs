@@ -0,0 +1,189 @@
+mod common;
+
+use std::fs;
+
+use clap::Parser;
+
+use common::{drive, TestRepo};
+use gitflow::{
+    args::Args,
+    backend::{MemoryBackend, MemoryRepo},
+    config::{self, CommitMessage, Config, KeyBindings},
+    input,
+    options::{Navigation, OptionItem, Options},
+    status::FileState,
+};
+
+fn with_config(repo: MemoryRepo, config: &[(&str, &str)]) -> MemoryBackend {
+    let git = MemoryBackend::new(repo);
+    for (key, value) in config {
+        git.repo_mut()
+            .config
+            .insert(key.to_string(), value.to_string());
+    }
+    git
+}
+
+#[test]
+fn later_sources_override_earlier_ones() {
+    let repo = TestRepo::uninitialized();
+    let user_file = repo.dir.join("config.toml");
+    fs::write(
+        &user_file,
+        "auto-upstream = true\nremote = \"origin\"\ntheme = \"dark\"\n\n[keys]\nquit = \"x\"\n",
+    )
+    .unwrap();
+    // outside the work tree, so it doesn't count
+    fs::write(repo.dir.join(".gitflow.toml"), "theme = \"none\"\n").unwrap();
+    let work = repo.dir.join("work");
+    fs::create_dir_all(work.join(".git")).unwrap();
+    fs::create_dir_all(work.join("src")).unwrap();
+    fs::write(
+        work.join(".gitflow.toml"),
+        "theme = \"light\"\ncommit-message = \"input\"\nprotected-branches = [\"main\"]\n",
+    )
+    .unwrap();
+    let git = with_config(
+        MemoryRepo::new("main"),
+        &[("gitflow.remote", "fork"), ("gitflow.keys.down", "n")],
+    );
+
+    let mut config = Config::load_from(Some(&user_file), &work.join("src"), &git).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            auto_upstream: Some(true),
+            remote: Some("fork".to_string()),
+            commit_message: Some(CommitMessage::Input),
+            theme: Some("light".to_string()),
+            themes: Default::default(),
+            protected_branches: Some(vec!["main".to_string()]),
+            keys: KeyBindings {
+                quit: Some('x'),
+                up: None,
+                down: Some('n'),
+            },
+        }
+    );
+    assert_eq!(config.keys.up(), 'k');
+
+    config.auto_upstream = Some(false);
+    config.merge_args(&Args::parse_from(["gitflow", "-a"]));
+    assert!(config.auto_upstream());
+}
+
+#[test]
+fn reads_git_config_keys() {
+    let git = with_config(
+        MemoryRepo::new("main"),
+        &[
+            ("gitflow.auto-upstream", "yes"),
+            ("gitflow.commit-message", "ai"),
+            ("gitflow.protected-branches", "main, release/*"),
+        ],
+    );
+    let config = Config::from_git(&git).unwrap();
+    assert!(config.auto_upstream());
+    assert_eq!(config.commit_message, Some(CommitMessage::Ai));
+    assert!(config.is_protected("main"));
+    assert!(config.is_protected("release/1.0"));
+    assert!(!config.is_protected("mainline"));
+}
+
+#[test]
+fn invalid_settings_are_errors() {
+    let repo = TestRepo::uninitialized();
+    let path = repo.dir.join("config.toml");
+    fs::write(&path, "colour = \"red\"\n").unwrap();
+    let err = Config::from_file(&path).unwrap_err();
+    assert!(format!("{:#}", err).contains("unknown field `colour`"));
+    assert!(err.to_string().contains(&path.display().to_string()));
+
+    for (key, value) in [
+        ("gitflow.auto-upstream", "maybe"),
+        ("gitflow.commit-message", "chatgpt"),
+        ("gitflow.keys.quit", "esc"),
+    ] {
+        let git = with_config(MemoryRepo::new("main"), &[(key, value)]);
+        let err = Config::from_git(&git).unwrap_err();
+        assert!(err.to_string().contains(key), "{}", err);
+    }
+}
+
+#[test]
+fn pushes_to_the_preferred_remote() {
+    let mut repo =
+        MemoryRepo::new("main").with_file("a.txt", FileState::Added, FileState::Unmodified);
+    repo.commits = 0;
+    repo.remotes = vec!["origin".to_string(), "fork".to_string()];
+    let git = MemoryBackend::new(repo);
+    config::install(Config {
+        remote: Some("fork".to_string()),
+        ..Default::default()
+    });
+    drive(&git, &["c", "i", "first", "y", "y"]);
+    assert_eq!(
+        git.commands(),
+        ["git commit -m 'first'", "git push --set-upstream fork main"]
+    );
+}
+
+#[test]
+fn commits_with_the_configured_message_provider() {
+    let git = MemoryBackend::new(
+        MemoryRepo::new("main")
+            .with_upstream("origin", "main", 0, 0)
+            .with_file("a.txt", FileState::Added, FileState::Unmodified),
+    );
+    config::install(Config {
+        commit_message: Some(CommitMessage::Input),
+        ..Default::default()
+    });
+    drive(&git, &["c", "add a", "y"]);
+    assert_eq!(git.commands(), ["git commit -m 'add a'"]);
+}
+
+#[test]
+fn protected_branches_are_not_force_pushed() {
+    let git = MemoryBackend::new(MemoryRepo::new("main").with_upstream("origin", "main", 2, 1));
+    config::install(Config {
+        protected_branches: Some(vec!["main".to_string()]),
+        ..Default::default()
+    });
+    drive(&git, &["f"]);
+    assert!(git.commands().is_empty());
+}
+
+#[test]
+fn prompts_follow_the_key_bindings() {
+    config::install(Config {
+        keys: KeyBindings {
+            quit: Some('x'),
+            up: Some('u'),
+            down: Some('d'),
+        },
+        ..Default::default()
+    });
+    let choose = |keys: &[&str]| {
+        input::script(keys);
+        Options {
+            prompt: "Choose one.",
+            options: ["add", "commit", "push"]
+                .into_iter()
+                .map(|desc| OptionItem {
+                    key: desc.chars().next().unwrap().to_ascii_uppercase(),
+                    desc: desc.to_string(),
+                    action: Box::new(move || Ok(desc)),
+                })
+                .collect(),
+        }
+        .execute()
+    };
+    assert_eq!(choose(&["d", "d", "u", "enter"]).unwrap(), "commit");
+    // q no longer quits
+    let err = choose(&["q", "x"]).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Navigation>(),
+        Some(Navigation::Quit)
+    ));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use std::process::Command;
+
+use common::{drive, strip_colors, TestRepo};
+use gitflow::{
+    backend::{DryRunBackend, GitBackend, MemoryBackend, MemoryRepo},
+    status::{FileState, GitStatus},
+};
+
+#[test]
+fn simulates_changes_without_making_them() {
+    let repo = MemoryRepo::new("main")
+        .with_upstream("origin", "main", 0, 0)
+        .with_file("a.txt", FileState::Unmodified, FileState::Untracked);
+    let git = DryRunBackend::new(MemoryBackend::new(repo));
+    drive(
+        &git,
+        &[
+            "a", // Unstaged: add all files
+            "y", "c", // FullyStaged: commit
+            "i", "add a", "y", "p", // FullyCommited: push
+            "y",
+        ],
+    );
+
+    // the flow went on as if every step had run
+    let snapshot = git.status().unwrap();
+    assert_eq!(GitStatus::of(snapshot.as_ref()), GitStatus::UpToDate);
+    // while the repository itself is untouched
+    assert!(git.inner().commands().is_empty());
+    let inner = git.inner().status().unwrap();
+    assert_eq!(GitStatus::of(inner.as_ref()), GitStatus::Unstaged);
+}
+
+#[test]
+fn shows_the_commands_it_would_run() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let head = repo.head();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args([
+            "--dry-run",
+            "-a",
+            "--keys",
+            "a,y,c,i,add a,y,p,y,o,b,feature,q",
+        ])
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let text = strip_colors(&String::from_utf8_lossy(&output.stdout));
+    for command in [
+        "git add --all",
+        "git commit -m 'add a'",
+        "git push origin main",
+        "git checkout -b feature",
+    ] {
+        assert!(
+            text.contains(&format!("==> Would run: {}\n", command)),
+            "{} missing from:\n{}",
+            command,
+            text
+        );
+    }
+
+    assert_eq!(repo.head(), head);
+    assert_eq!(repo.origin_head("main"), head);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "?? a.txt");
+    assert_eq!(repo.git(&["branch", "--show-current"]), "main");
+}
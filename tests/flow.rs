@@ -0,0 +1,540 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use anyhow::anyhow;
+use clap::Parser;
+use common::{drive, TestRepo};
+use gitflow::{
+    args::Args,
+    backend::{CliBackend, MemoryBackend, MemoryRepo},
+    flow::{self, FlowOutcome, EXIT_CONFLICTS, EXIT_ERROR, EXIT_GIT},
+    git::{GitError, BACKUP_REF},
+    input,
+    status::{FileState, GitOperation, GitStatus},
+};
+
+#[test]
+fn add_commit_push() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    drive(
+        &CliBackend,
+        &[
+            "a", // Unstaged: add all files
+            "y", "c", // FullyStaged: commit
+            "i", "add a", "y", "p", // FullyCommited: push
+            "y",
+        ],
+    );
+    assert_eq!(repo.origin_head("main"), repo.head());
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "add a");
+    assert!(matches!(repo.status(), GitStatus::UpToDate));
+}
+
+#[test]
+fn first_commit_sets_upstream() {
+    let mut repo = TestRepo::initialized();
+    repo.origin = Some(repo.add_origin());
+    repo.write("a.txt", "a\n");
+    repo.git(&["add", "a.txt"]);
+    drive(
+        &CliBackend,
+        &[
+            "c", // Initialized: make the first commit
+            "i", "initial", "y", "y", // publish it
+        ],
+    );
+    assert_eq!(
+        repo.git(&["rev-parse", "--abbrev-ref", "main@{upstream}"]),
+        "origin/main"
+    );
+    assert_eq!(repo.origin_head("main"), repo.head());
+}
+
+#[test]
+fn gitignore_goes_to_the_top_of_the_work_tree() {
+    let repo = TestRepo::initialized();
+    fs::create_dir(repo.dir.join("sub")).unwrap();
+    repo.write("sub/a.txt", "a\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["-a", "--keys", "g,target,q"])
+        .current_dir(repo.dir.join("sub"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(repo.read(".gitignore"), "target\n");
+    assert!(!repo.dir.join("sub").join(".gitignore").exists());
+}
+
+#[test]
+fn merge_remote_branch() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("feature.txt", "feature\n");
+    repo.git(&["push", "-q", "origin", "feature"]);
+    repo.git(&["checkout", "-q", "main"]);
+    drive(
+        &CliBackend,
+        &[
+            "m", // UpToDate: merge
+            "r", // a remote branch
+            "y", // from the only remote
+            "f", // find feature
+            "enter",
+        ],
+    );
+    assert_eq!(repo.read("feature.txt"), "feature\n");
+    assert!(matches!(repo.status(), GitStatus::FullyCommited));
+}
+
+#[test]
+fn checkout_looks_up_the_upstream_again() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.git(&["push", "-q"]);
+    repo.git(&["branch", "old", "HEAD~1"]);
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["--output", "json", "-a", "--keys", "c,o,l,d,enter"])
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let statuses: Vec<_> = events
+        .iter()
+        .filter(|event| event["event"] == "status")
+        .collect();
+    let last = statuses.last().unwrap();
+    assert_eq!(last["repository"]["branch"], "old");
+    // old has no upstream, so nothing is offered against main's
+    assert_eq!(last["repository"]["upstream"], serde_json::Value::Null);
+    assert_ne!(last["status"], "Behind");
+    let prompt = events
+        .iter()
+        .rev()
+        .find(|event| event["event"] == "prompt")
+        .unwrap();
+    for option in prompt["options"].as_array().unwrap() {
+        let description = option["description"].as_str().unwrap();
+        assert!(
+            !["Pull", "Merge the remote", "Force push"]
+                .iter()
+                .any(|offer| description.starts_with(offer)),
+            "{}",
+            description
+        );
+    }
+}
+
+#[test]
+fn local_upstream_without_a_remote() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("topic.txt", "topic\n");
+    // the upstream is a local branch, so it has no remote part
+    repo.git(&["branch", "-q", "--set-upstream-to=main"]);
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["-a", "--keys", "q"])
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn merge_one_of_many_local_branches() {
+    let repo = TestRepo::with_origin();
+    for n in 1..=12 {
+        repo.git(&["branch", &format!("topic-{:02}", n)]);
+    }
+    repo.git(&["checkout", "-q", "topic-11"]);
+    repo.commit_file("topic.txt", "topic\n");
+    repo.git(&["checkout", "-q", "main"]);
+    drive(
+        &CliBackend,
+        &[
+            "m", // UpToDate: merge
+            "l", // a local branch
+            "1", // topic-01, topic-10, topic-11 and topic-12 match
+            "1", // only topic-11 matches
+            "enter",
+        ],
+    );
+    assert_eq!(repo.read("topic.txt"), "topic\n");
+    assert!(matches!(repo.status(), GitStatus::FullyCommited));
+}
+
+#[test]
+fn enter_pushes_when_fully_committed() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    drive(
+        &CliBackend,
+        &[
+            "enter", // FullyCommited: push is highlighted
+            "enter", // to the upstream
+        ],
+    );
+    assert_eq!(repo.head(), repo.origin_head("main"));
+}
+
+#[test]
+fn back_out_of_a_nested_prompt() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["branch", "feature"]);
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("topic.txt", "topic\n");
+    repo.git(&["checkout", "-q", "main"]);
+    drive(
+        &CliBackend,
+        &[
+            "m",   // UpToDate: merge
+            "r",   // a remote branch
+            "esc", // back to local or remote
+            "esc", // back to the status
+            "c",   // UpToDate: checkout
+            "esc", // no branch after all
+            "m",   // merge
+            "l",   // a local branch
+            "t", "enter",
+        ],
+    );
+    assert_eq!(repo.read("topic.txt"), "topic\n");
+    assert_eq!(repo.git(&["branch", "--show-current"]), "main");
+}
+
+#[test]
+fn quit_ends_the_flow() {
+    let _repo = TestRepo::with_origin();
+    input::script(&["m", "l", "q"]);
+    let outcome = flow::run(
+        &CliBackend,
+        Args::parse_from(["gitflow", "--auto-upstream"]),
+    );
+    assert_eq!(outcome.unwrap(), FlowOutcome::Quit);
+}
+
+#[test]
+fn unresolved_conflicts_end_the_flow() {
+    let git = MemoryBackend::new(
+        MemoryRepo::new("main")
+            .with_upstream("origin", "main", 0, 0)
+            .with_conflict("a.txt"),
+    );
+    input::script(&["n"]);
+    let outcome = flow::run(&git, Args::parse_from(["gitflow", "--auto-upstream"]));
+    assert_eq!(outcome.unwrap(), FlowOutcome::ConflictsPending);
+    assert_eq!(FlowOutcome::ConflictsPending.exit_code(), EXIT_CONFLICTS);
+}
+
+#[test]
+fn exit_codes_of_failures() {
+    let git_failed = anyhow!(GitError::new("Failed to push")).context("while pushing");
+    assert_eq!(flow::exit_code(&git_failed), EXIT_GIT);
+    assert_eq!(flow::exit_code(&anyhow!("No remote")), EXIT_ERROR);
+}
+
+#[test]
+fn pull_with_rebase_when_behind() {
+    let repo = TestRepo::with_origin();
+    repo.push_from_elsewhere("remote.txt", "remote\n");
+    drive(&CliBackend, &["r"]);
+    assert_eq!(repo.read("remote.txt"), "remote\n");
+    assert_eq!(repo.head(), repo.origin_head("main"));
+}
+
+#[test]
+fn resolve_merge_conflict() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("README.md", "feature\n");
+    repo.git(&["checkout", "-q", "main"]);
+    repo.commit_file("README.md", "main\n");
+    repo.git_fails(&["merge", "feature"]);
+    repo.write("README.md", "resolved\n");
+    drive(
+        &CliBackend,
+        &[
+            "y", // Merging: mark resolved
+            "c", // continue the merge
+        ],
+    );
+    assert!(matches!(repo.status(), GitStatus::FullyCommited));
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%p"]).split(' ').count(),
+        2
+    );
+    assert_eq!(repo.read("README.md"), "resolved\n");
+}
+
+#[test]
+fn abort_rebase() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("README.md", "feature\n");
+    repo.git(&["checkout", "-q", "main"]);
+    repo.commit_file("README.md", "main\n");
+    let head = repo.head();
+    repo.git_fails(&["rebase", "feature"]);
+    drive(&CliBackend, &["a"]);
+    assert!(matches!(repo.status(), GitStatus::FullyCommited));
+    assert_eq!(repo.head(), head);
+}
+
+#[test]
+fn memory_initializes_repository() {
+    let git = MemoryBackend::default();
+    drive(&git, &["y"]);
+    assert!(git.repo().initialized);
+    assert_eq!(git.commands(), ["git init"]);
+}
+
+#[test]
+fn stage_and_unstage_single_files() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.commit_file("b.txt", "b\n");
+    repo.git(&["push", "-q"]);
+    repo.write("a.txt", "changed\n");
+    fs::remove_file(repo.dir.join("b.txt")).unwrap();
+    repo.write("c.txt", "c\n");
+    drive(
+        &CliBackend,
+        &[
+            "s", // Unstaged: stage some files
+            "d", // only the deleted ones
+            "enter", "s", // PartiallyStaged: stage some files
+            "space", "down", "space", "enter",
+        ],
+    );
+    assert_eq!(
+        repo.git(&["status", "--porcelain"]),
+        "M  a.txt\nD  b.txt\nA  c.txt"
+    );
+
+    drive(&CliBackend, &["u", "n", "enter"]);
+    assert_eq!(
+        repo.git(&["status", "--porcelain"]),
+        "M  a.txt\nD  b.txt\n?? c.txt"
+    );
+}
+
+/// A pushed file of twenty numbered lines, with the second and the nineteenth changed.
+fn with_two_hunks() -> TestRepo {
+    let repo = TestRepo::with_origin();
+    let lines: Vec<_> = (1..=20).map(|n| n.to_string()).collect();
+    repo.commit_file("n.txt", &format!("{}\n", lines.join("\n")));
+    repo.git(&["push", "-q"]);
+    let changed: Vec<_> = lines
+        .iter()
+        .map(|line| match line.as_str() {
+            "2" | "19" => format!("{} changed", line),
+            _ => line.clone(),
+        })
+        .collect();
+    repo.write("n.txt", &format!("{}\n", changed.join("\n")));
+    repo
+}
+
+#[test]
+fn stage_single_hunks() {
+    let repo = with_two_hunks();
+    drive(&CliBackend, &["p", "n", "y"]);
+    let staged = repo.git(&["diff", "--cached"]);
+    assert!(!staged.contains("+2 changed"));
+    assert!(staged.contains("+19 changed"));
+    let unstaged = repo.git(&["diff"]);
+    assert!(unstaged.contains("+2 changed"));
+    assert!(!unstaged.contains("+19 changed"));
+}
+
+#[test]
+fn edit_a_hunk() {
+    let repo = with_two_hunks();
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["-a", "--keys", "p,e,d"])
+        .env("GIT_EDITOR", "sed -i -e 's/^+2 changed/+2 edited/'")
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let index = repo.git(&["show", ":n.txt"]);
+    assert_eq!(index.lines().nth(1), Some("2 edited"));
+    assert_eq!(index.lines().nth(18), Some("19"));
+}
+
+#[test]
+fn an_edit_without_changes_asks_again() {
+    let repo = with_two_hunks();
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        // stage the first hunk, edit the second down to nothing, then stage it as it is
+        .args(["-a", "--keys", "p,y,e,y"])
+        .env("GIT_EDITOR", "sed -i -e '/^[-+]/d'")
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("no changes left"));
+    assert_eq!(repo.git(&["diff"]), "");
+    assert_eq!(repo.git(&["status", "--porcelain"]), "M  n.txt");
+}
+
+#[test]
+fn split_a_hunk() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("n.txt", "1\n2\n3\n4\n5\n");
+    repo.git(&["push", "-q"]);
+    repo.write("n.txt", "1\n2 changed\n3\n4 changed\n5\n");
+    drive(&CliBackend, &["p", "s", "n", "y"]);
+    assert_eq!(repo.git(&["show", ":n.txt"]), "1\n2\n3\n4 changed\n5");
+}
+
+#[test]
+fn stage_both_parts_of_a_split_hunk() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("n.txt", "1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+    repo.git(&["push", "-q"]);
+    repo.write("n.txt", "1\n2 changed\n3\n4\n5\n6\n7\n8 changed\n9\n");
+    drive(&CliBackend, &["p", "s", "y", "y"]);
+    assert_eq!(repo.git(&["diff"]), "");
+    assert_eq!(repo.git(&["status", "--porcelain"]), "M  n.txt");
+}
+
+#[test]
+fn unstage_a_rename() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.git(&["push", "-q"]);
+    repo.git(&["mv", "a.txt", "b.txt"]);
+    drive(&CliBackend, &["u", "enter"]);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "D a.txt\n?? b.txt");
+}
+
+#[test]
+fn discard_unstaged_changes() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.commit_file("b.txt", "b\n");
+    repo.git(&["push", "-q"]);
+    repo.write("a.txt", "staged\n");
+    repo.git(&["add", "a.txt"]);
+    repo.write("a.txt", "unstaged\n");
+    repo.write("b.txt", "changed\n");
+    repo.write("c.txt", "c\n");
+    drive(&CliBackend, &["d", "a", "enter", "y"]);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "M  a.txt");
+    assert!(!repo.dir.join(".git").join("gitflow-backup.index").exists());
+    assert_eq!(
+        fs::read_to_string(repo.dir.join("a.txt")).unwrap(),
+        "staged\n"
+    );
+
+    // everything lost is backed up
+    repo.git(&[
+        "restore", "--source", BACKUP_REF, "--", "a.txt", "b.txt", "c.txt",
+    ]);
+    assert_eq!(
+        repo.git(&["status", "--porcelain"]),
+        "MM a.txt\n M b.txt\n?? c.txt"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.dir.join("a.txt")).unwrap(),
+        "unstaged\n"
+    );
+}
+
+#[test]
+fn discard_staged_changes() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.git(&["push", "-q"]);
+    repo.write("a.txt", "changed\n");
+    repo.write("n.txt", "n\n");
+    repo.git(&["add", "--all"]);
+    // keeping the new file
+    drive(&CliBackend, &["d", "space", "enter", "y"]);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "A  n.txt");
+    assert_eq!(fs::read_to_string(repo.dir.join("a.txt")).unwrap(), "a\n");
+
+    drive(&CliBackend, &["d", "enter", "y"]);
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+    assert!(!repo.dir.join("n.txt").exists());
+    assert_eq!(
+        repo.git(&["reflog", "--format=%gs", BACKUP_REF]),
+        "gitflow: changes discarded\ngitflow: changes discarded"
+    );
+    assert_eq!(repo.git(&["show", &format!("{}:n.txt", BACKUP_REF)]), "n");
+}
+
+#[test]
+fn memory_discards_after_a_backup() {
+    let mut repo = MemoryRepo::new("main")
+        .with_upstream("origin", "main", 0, 0)
+        .with_file("a.txt", FileState::Unmodified, FileState::Modified)
+        .with_file("b.txt", FileState::Unmodified, FileState::Untracked);
+    repo.diffs
+        .insert("a.txt".to_string(), "@@ -1 +1 @@\n-a\n+b\n".to_string());
+    let git = MemoryBackend::new(repo);
+    // backing out of the confirmation discards nothing
+    drive(&git, &["d", "a", "enter", "esc", "esc"]);
+    assert!(git.commands().is_empty());
+
+    drive(&git, &["d", "a", "enter", "y"]);
+    let repo = git.repo();
+    assert!(repo.entries.is_empty());
+    assert_eq!(repo.backups, 1);
+    assert_eq!(
+        git.commands(),
+        [
+            "git update-ref --create-reflog -m gitflow: changes discarded refs/gitflow/discarded",
+            "git restore --worktree -- a.txt",
+            "git clean -f -q -- b.txt",
+        ]
+    );
+}
+
+#[test]
+fn memory_stages_and_commits() {
+    let git = MemoryBackend::new(
+        MemoryRepo::new("main")
+            .with_upstream("origin", "main", 0, 0)
+            .with_file("a.txt", FileState::Unmodified, FileState::Untracked),
+    );
+    drive(&git, &["a", "y", "c", "i", "add a", "y"]);
+    let repo = git.repo();
+    assert!(repo.entries.is_empty());
+    assert_eq!(repo.ahead, 1);
+    assert_eq!(git.commands(), ["git add --all", "git commit -m 'add a'"]);
+}
+
+#[test]
+fn memory_force_pushes_when_diverged() {
+    let git = MemoryBackend::new(MemoryRepo::new("main").with_upstream("origin", "main", 2, 1));
+    drive(&git, &["f"]);
+    assert_eq!((git.repo().ahead, git.repo().behind), (0, 0));
+    assert_eq!(git.commands(), ["git push --force-with-lease origin main"]);
+}
+
+#[test]
+fn memory_continues_cherry_pick() {
+    let mut repo = MemoryRepo::new("main")
+        .with_upstream("origin", "main", 0, 0)
+        .with_conflict("a.txt");
+    repo.operation = Some(GitOperation::CherryPick);
+    let git = MemoryBackend::new(repo);
+    drive(&git, &["y", "c"]);
+    assert_eq!(git.repo().operation, None);
+    assert_eq!(
+        git.commands(),
+        ["git add --all", "git cherry-pick --continue"]
+    );
+}
@@ -0,0 +1,92 @@
+use gitflow::hunks::{FileDiff, Hunk};
+
+const DIFF: &str = "\
+diff --git a/a.txt b/a.txt
+index 1234567..89abcde 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,7 +1,7 @@ fn main
+ one
+-two
++TWO
+ three
+ four
+ five
+-six
++SIX
+ seven
+@@ -20,2 +20,3 @@
+ twenty
++twenty-one
+ twenty-two
+";
+
+fn diff() -> FileDiff {
+    FileDiff::parse(DIFF).unwrap().unwrap()
+}
+
+#[test]
+fn parses_the_hunks_of_a_file() {
+    let diff = diff();
+    assert_eq!(diff.header.len(), 4);
+    assert_eq!(diff.hunks.len(), 2);
+    let hunk = &diff.hunks[0];
+    assert_eq!((hunk.old_start, hunk.old_len), (1, 7));
+    assert_eq!(hunk.section, "fn main");
+    assert_eq!(hunk.lines.len(), 9);
+    assert_eq!(
+        diff.hunks[1].to_string(),
+        "@@ -20,2 +20,3 @@\n twenty\n+twenty-one\n twenty-two\n"
+    );
+
+    assert_eq!(FileDiff::parse("").unwrap(), None);
+    assert!(FileDiff::parse("@@ -x +1 @@\n").is_err());
+}
+
+#[test]
+fn splits_around_context() {
+    let parts = diff().hunks[0].split();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(
+        parts[0].to_string(),
+        "@@ -1,5 +1,5 @@ fn main\n one\n-two\n+TWO\n three\n four\n five\n"
+    );
+    assert_eq!(
+        parts[1].to_string(),
+        "@@ -3,5 +3,5 @@ fn main\n three\n four\n five\n-six\n+SIX\n seven\n"
+    );
+    assert!(!parts[1].can_split());
+    assert_eq!(diff().hunks[1].split(), [diff().hunks[1].clone()]);
+}
+
+#[test]
+fn patches_only_the_chosen_hunks() {
+    let diff = diff();
+    let patch = diff.patch(&[diff.hunks[1].clone()]);
+    // the skipped hunk adds no line, so the other one stays where it was in the old file
+    assert!(patch.starts_with("diff --git a/a.txt b/a.txt\n"));
+    assert!(patch.contains("+++ b/a.txt\n@@ -20,2 +20,3 @@\n twenty\n"));
+
+    let added = Hunk {
+        old_start: 1,
+        old_len: 0,
+        new_start: 1,
+        new_len: 1,
+        section: String::new(),
+        lines: vec!["+zero".to_string()],
+    };
+    let patch = diff.patch(&[diff.hunks[1].clone(), added]);
+    assert!(patch.contains("@@ -0,0 +1,1 @@\n+zero\n"));
+    assert!(patch.contains("@@ -20,2 +21,3 @@\n"));
+}
+
+#[test]
+fn edited_hunks_are_recounted() {
+    let hunk = &diff().hunks[1];
+    let edited = hunk
+        .edited("# a comment\n twenty\n+twenty-one\n+twenty-one and a half\n twenty-two\n")
+        .unwrap();
+    assert_eq!(edited.header(), "@@ -20,2 +20,4 @@");
+    assert!(hunk.edited(" twenty\n twenty-two\n").is_err());
+    assert!(hunk.edited("twenty\n+21\n").is_err());
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use common::TestRepo;
+use crossterm::event::KeyCode;
+use gitflow::{
+    flow::{EXIT_CONFLICTS, EXIT_GIT, EXIT_QUIT},
+    input::{self, parse_key, InputSource, Scripted},
+};
+
+/// Runs the binary in `repo` and returns its exit code.
+fn gitflow_status(repo: &TestRepo, args: &[&str], stdin: &str) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(args)
+        .current_dir(&repo.dir)
+        .env_remove("GITFLOW_KEYS")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait().unwrap().code().unwrap()
+}
+
+fn gitflow(repo: &TestRepo, args: &[&str], stdin: &str) {
+    assert_eq!(gitflow_status(repo, args, stdin), EXIT_QUIT);
+}
+
+#[test]
+fn parses_keys() {
+    assert_eq!(parse_key("a").unwrap(), KeyCode::Char('a'));
+    assert_eq!(parse_key(",").unwrap(), KeyCode::Char(','));
+    assert_eq!(parse_key("Enter").unwrap(), KeyCode::Enter);
+    assert_eq!(parse_key("space").unwrap(), KeyCode::Char(' '));
+    assert_eq!(parse_key("up").unwrap(), KeyCode::Up);
+    assert!(parse_key("").is_err());
+    assert!(parse_key("nope").is_err());
+}
+
+#[test]
+fn splits_keys_on_commas() {
+    let mut keys = Scripted::from_keys(r"a,git commit -m 'x\, y',,esc");
+    assert_eq!(keys.read_key().unwrap(), KeyCode::Char('a'));
+    assert_eq!(keys.read_line().unwrap(), "git commit -m 'x, y'");
+    assert_eq!(keys.read_line().unwrap(), "");
+    assert_eq!(keys.read_key().unwrap(), KeyCode::Esc);
+    let err = keys.read_key().unwrap_err();
+    assert!(err.is::<input::ScriptExhausted>());
+}
+
+#[test]
+fn reads_replay_file() {
+    let repo = TestRepo::uninitialized();
+    let path = repo.dir.join("replay");
+    fs::write(&path, "# first commit\na\n\n  y\n").unwrap();
+    let mut replay = Scripted::from_file(&path).unwrap();
+    assert_eq!(replay.read_key().unwrap(), KeyCode::Char('a'));
+    assert_eq!(replay.read_line().unwrap(), "  y");
+    assert!(replay.read_key().is_err());
+    assert!(Scripted::from_file(&repo.dir.join("missing")).is_err());
+}
+
+#[test]
+fn keys_flag_runs_unattended() {
+    let repo = TestRepo::initialized();
+    repo.write("a.txt", "a\n");
+    gitflow(&repo, &["--keys", r"a,y,c,i,first\, commit,y"], "");
+    assert_eq!(repo.git(&["log", "--format=%s"]), "first, commit");
+}
+
+#[test]
+fn replay_file_runs_unattended() {
+    let repo = TestRepo::initialized();
+    repo.write("a.txt", "a\n");
+    let replay = repo.dir.join(".git").join("replay");
+    fs::write(
+        &replay,
+        "# stage everything\na\ny\n# commit it\nc\ni\nfrom file\ny\n",
+    )
+    .unwrap();
+    gitflow(&repo, &["--replay", replay.to_str().unwrap()], "");
+    assert_eq!(repo.git(&["log", "--format=%s"]), "from file");
+}
+
+#[test]
+fn stdin_lines_run_unattended() {
+    let repo = TestRepo::initialized();
+    repo.write("a.txt", "a\n");
+    gitflow(&repo, &["--stdin"], "a\ny\nc\ni\npiped\ny\n");
+    assert_eq!(repo.git(&["log", "--format=%s"]), "piped");
+}
+
+#[test]
+fn exit_codes() {
+    let repo = TestRepo::with_origin();
+    assert_eq!(gitflow_status(&repo, &["--keys", "q"], ""), EXIT_QUIT);
+
+    // fetching from a remote that is gone
+    fs::remove_dir_all(repo.origin.as_ref().unwrap()).unwrap();
+    assert_eq!(gitflow_status(&repo, &["-a", "--keys", "f"], ""), EXIT_GIT);
+
+    // a stash that doesn't apply cleanly leaves conflicts without a merge in progress
+    repo.write("README.md", "stashed\n");
+    repo.git(&["stash", "-q"]);
+    repo.commit_file("README.md", "committed\n");
+    repo.git_fails(&["stash", "pop", "-q"]);
+    assert_eq!(
+        gitflow_status(&repo, &["-a", "--keys", "n"], ""),
+        EXIT_CONFLICTS
+    );
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "native")]
+
+mod common;
+
+use common::TestRepo;
+use gitflow::backend::{CliBackend, GitBackend, NativeBackend};
+
+fn assert_same_snapshot(repo: &TestRepo) {
+    let mut cli = CliBackend.status().unwrap().unwrap();
+    let mut native = NativeBackend::default().status().unwrap().unwrap();
+    cli.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    native.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(
+        format!("{:?}", cli),
+        format!("{:?}", native),
+        "in {:?}",
+        repo.dir
+    );
+}
+
+#[test]
+fn matches_cli_status() {
+    let repo = TestRepo::with_origin();
+    repo.push_from_elsewhere("remote.txt", "remote\n");
+    repo.commit_file("local.txt", "local\n");
+    repo.write("README.md", "changed\n");
+    repo.write("staged.txt", "staged\n");
+    repo.git(&["add", "staged.txt"]);
+    repo.write("untracked.txt", "untracked\n");
+    assert_same_snapshot(&repo);
+}
+
+#[test]
+fn matches_cli_before_first_commit() {
+    let repo = TestRepo::initialized();
+    repo.write("a.txt", "a\n");
+    assert_same_snapshot(&repo);
+    assert!(NativeBackend::default()
+        .status()
+        .unwrap()
+        .unwrap()
+        .oid
+        .is_none());
+}
+
+#[test]
+fn lists_branches() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["branch", "feature"]);
+    repo.git(&["push", "-q", "origin", "feature"]);
+    let native = NativeBackend::default();
+    assert_eq!(native.branches(None).unwrap(), ["feature", "main"]);
+    assert_eq!(
+        native.branches(Some("origin")).unwrap(),
+        ["feature", "main"]
+    );
+    assert_eq!(native.remotes().unwrap(), ["origin"]);
+    assert_eq!(native.ahead_behind("origin/main").unwrap(), (0, 0));
+}
+
+#[test]
+fn outside_repository() {
+    let _repo = TestRepo::uninitialized();
+    assert!(NativeBackend::default().status().unwrap().is_none());
+}
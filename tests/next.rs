@@ -0,0 +1,104 @@
+mod common;
+
+use std::process::Command;
+
+use common::TestRepo;
+use gitflow::{
+    backend::{MemoryBackend, MemoryRepo},
+    flow,
+    next::{to_json, to_text},
+    status::{FileState, GitStatus},
+};
+use serde_json::{json, Value};
+
+fn gitflow_next(repo: &TestRepo, json: bool) -> String {
+    let mut args = vec!["next"];
+    if json {
+        args.push("--json");
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(args)
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn recommends_pushing_local_commits() {
+    let git = MemoryBackend::new(MemoryRepo::new("main").with_upstream("origin", "main", 1, 0));
+    let snapshot = flow::capture(&git).unwrap();
+    let status = GitStatus::of(snapshot.as_ref());
+    assert_eq!(status, GitStatus::FullyCommited);
+
+    let menu = flow::menu(&git, snapshot.as_ref()).unwrap();
+    assert_eq!(
+        to_text(status, &menu),
+        "FullyCommited: Files are all committed, you can chose:\n\
+         \x20 [M] Merge.\n\
+         \x20 [L] Pull.\n\
+         \x20 [O] Checkout\n\
+         * [P] Push to remote\n"
+    );
+    let json = to_json(status, &menu);
+    assert_eq!(json["recommended"], "P");
+    assert_eq!(json["actions"][3]["recommended"], true);
+    // nothing was run to build the menu
+    assert!(git.commands().is_empty());
+}
+
+#[test]
+fn no_push_while_behind_with_changes() {
+    let git = MemoryBackend::new(
+        MemoryRepo::new("main")
+            .with_upstream("origin", "main", 1, 2)
+            .with_file("a.txt", FileState::Unmodified, FileState::Modified),
+    );
+    let snapshot = flow::capture(&git).unwrap();
+    let status = GitStatus::of(snapshot.as_ref());
+    assert_eq!(status, GitStatus::PartiallyCommited);
+    let menu = flow::menu(&git, snapshot.as_ref()).unwrap();
+    let keys: Vec<_> = menu.options.iter().map(|option| option.key).collect();
+    assert_eq!(keys, ['A', 'O']);
+}
+
+#[test]
+fn prints_the_status_of_a_repository() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let text = gitflow_next(&repo, false);
+    assert_eq!(
+        text,
+        "Unstaged: Files unstaged.\n\
+         * [A] Add all files.\n\
+         \x20 [S] Stage some files.\n\
+         \x20 [P] Review and stage hunks.\n\
+         \x20 [D] Discard changes to some files.\n\
+         \x20 [O] Checkout a branch.\n\
+         \x20 [B] Create a branch.\n"
+    );
+}
+
+#[test]
+fn prints_json_without_prompting_for_an_upstream() {
+    let repo = TestRepo::with_origin();
+    repo.git(&["branch", "--unset-upstream"]);
+    repo.push_from_elsewhere("remote.txt", "remote\n");
+    let json: Value = serde_json::from_str(&gitflow_next(&repo, true)).unwrap();
+    // the remote branch with the same name is used
+    assert_eq!(json["status"], "Behind");
+    assert_eq!(json["recommended"], "R");
+    assert_eq!(
+        json["actions"][0],
+        json!({"key": "R", "description": "Pull with rebase.", "recommended": true})
+    );
+}
+
+#[test]
+fn outside_a_repository() {
+    let repo = TestRepo::uninitialized();
+    let json: Value = serde_json::from_str(&gitflow_next(&repo, true)).unwrap();
+    assert_eq!(json["status"], "Uninitialized");
+    assert_eq!(json["actions"].as_array().unwrap().len(), 1);
+}
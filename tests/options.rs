@@ -0,0 +1,275 @@
+use gitflow::{
+    input,
+    options::{
+        breadcrumbs, fuzzy_score, Candidate, Check, CheckFilter, Checklist, Finder, Navigation,
+        OptionItem, Options, Picker,
+    },
+};
+
+fn pick(count: usize, keys: &[&str]) -> String {
+    input::script(keys);
+    Picker {
+        prompt: "Pick one.",
+        items: (1..=count).map(|n| format!("branch-{}", n)).collect(),
+    }
+    .pick()
+    .unwrap()
+}
+
+#[test]
+fn single_digit_picks_immediately() {
+    assert_eq!(pick(9, &["9"]), "branch-9");
+    assert_eq!(pick(25, &["3"]), "branch-3");
+}
+
+#[test]
+fn ambiguous_digits_wait_for_more() {
+    assert_eq!(pick(25, &["2", "5"]), "branch-25");
+    assert_eq!(pick(25, &["1", "enter"]), "branch-1");
+    assert_eq!(pick(10, &["1", "0"]), "branch-10");
+}
+
+#[test]
+fn out_of_range_numbers_are_rejected() {
+    assert_eq!(pick(12, &["0", "1", "3", "7"]), "branch-7");
+    assert_eq!(pick(12, &["1", "backspace", "4"]), "branch-4");
+}
+
+#[test]
+fn pages_through_long_lists() {
+    assert_eq!(
+        pick(25, &[">", ">", ">", "<", "1", "2", "enter"]),
+        "branch-12"
+    );
+    assert_eq!(pick(25, &["right", "left", "left", "8"]), "branch-8");
+}
+
+#[test]
+fn empty_list_is_an_error() {
+    input::script(&["1"]);
+    let picker = Picker {
+        prompt: "Pick one.",
+        items: vec![],
+    };
+    assert!(picker.pick().is_err());
+}
+
+fn candidate(value: &str, recency: i64) -> Candidate {
+    Candidate {
+        value: value.to_string(),
+        preview: Some(format!("last commit of {}", value)),
+        recency,
+    }
+}
+
+fn find(keys: &[&str]) -> String {
+    input::script(keys);
+    Finder {
+        prompt: "Find one.",
+        candidates: vec![
+            candidate("main", 10),
+            candidate("feature/login-form", 30),
+            candidate("feature/logout", 20),
+            candidate("fix/flaky-login", 40),
+        ],
+    }
+    .find()
+    .unwrap()
+}
+
+#[test]
+fn fuzzy_scores() {
+    assert_eq!(fuzzy_score("", "main"), Some(0));
+    assert_eq!(fuzzy_score("xyz", "main"), None);
+    assert_eq!(fuzzy_score("nm", "main"), None);
+    assert!(fuzzy_score("MAIN", "main").is_some());
+    // consecutive characters beat scattered ones
+    assert!(fuzzy_score("log", "feature/login") > fuzzy_score("log", "fix/large-object-graph"));
+    // word starts beat characters in the middle of a word
+    assert!(fuzzy_score("lf", "feature/login-form") > fuzzy_score("lf", "fix/selfie"));
+    // the best of several ways to match counts
+    assert_eq!(fuzzy_score("fl", "fix/flaky"), fuzzy_score("fl", "flaky"));
+}
+
+#[test]
+fn empty_query_ranks_by_recency() {
+    assert_eq!(find(&["enter"]), "fix/flaky-login");
+    assert_eq!(find(&["down", "down", "enter"]), "feature/logout");
+    assert_eq!(find(&["down", "up", "up", "enter"]), "fix/flaky-login");
+}
+
+#[test]
+fn typing_filters_and_ranks_by_match() {
+    assert_eq!(find(&["l", "o", "g", "o", "enter"]), "feature/logout");
+    assert_eq!(find(&["f", "l", "enter"]), "fix/flaky-login");
+    assert_eq!(find(&["l", "f", "enter"]), "feature/login-form");
+    assert_eq!(find(&["m", "a", "i", "n", "enter"]), "main");
+}
+
+#[test]
+fn editing_the_query() {
+    assert_eq!(find(&["x", "x", "enter", "esc", "m", "enter"]), "main");
+    assert_eq!(find(&["m", "z", "backspace", "enter"]), "main");
+}
+
+fn choose(default: Option<char>, keys: &[&str]) -> &'static str {
+    input::script(keys);
+    let options = Options {
+        prompt: "Choose one.",
+        options: ["add", "commit", "push"]
+            .into_iter()
+            .map(|desc| OptionItem {
+                key: desc.chars().next().unwrap().to_ascii_uppercase(),
+                desc: desc.to_string(),
+                action: Box::new(move || Ok(desc)),
+            })
+            .collect(),
+    };
+    match default {
+        Some(key) => options.execute_with_default(key),
+        None => options.execute(),
+    }
+    .unwrap()
+}
+
+#[test]
+fn keys_pick_options_directly() {
+    assert_eq!(choose(None, &["c"]), "commit");
+    assert_eq!(choose(None, &["x", "P"]), "push");
+}
+
+#[test]
+fn enter_picks_the_highlighted_option() {
+    assert_eq!(choose(None, &["enter"]), "add");
+    assert_eq!(choose(Some('p'), &["enter"]), "push");
+    assert_eq!(choose(Some('P'), &["up", "enter"]), "commit");
+}
+
+#[test]
+fn cursor_moves_and_stops_at_the_ends() {
+    assert_eq!(choose(None, &["down", "down", "enter"]), "push");
+    assert_eq!(choose(None, &["j", "j", "k", "enter"]), "commit");
+    assert_eq!(choose(None, &["up", "up", "enter"]), "add");
+    assert_eq!(choose(None, &["end", "up", "enter"]), "push");
+    assert_eq!(choose(Some('p'), &["home", "enter"]), "add");
+}
+
+/// A prompt offering "inner", which asks a nested prompt offering "leaf".
+fn nested(keys: &[&str]) -> anyhow::Result<Vec<String>> {
+    input::script(keys);
+    Options {
+        prompt: "Outer.",
+        options: vec![OptionItem {
+            key: 'I',
+            desc: "Inner.".to_string(),
+            action: Box::new(|| {
+                Options {
+                    prompt: "Inner.",
+                    options: vec![OptionItem {
+                        key: 'L',
+                        desc: "Leaf!".to_string(),
+                        action: Box::new(|| Ok(breadcrumbs())),
+                    }],
+                }
+                .execute()
+            }),
+        }],
+    }
+    .execute()
+}
+
+#[test]
+fn breadcrumbs_follow_the_picked_options() {
+    assert_eq!(nested(&["i", "l"]).unwrap(), ["Inner", "Leaf"]);
+    assert!(breadcrumbs().is_empty());
+}
+
+#[test]
+fn esc_goes_back_to_the_previous_prompt() {
+    assert_eq!(nested(&["i", "esc", "i", "l"]).unwrap(), ["Inner", "Leaf"]);
+    let err = nested(&["esc"]).unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Back));
+    assert!(breadcrumbs().is_empty());
+}
+
+#[test]
+fn q_quits_from_any_depth() {
+    let err = nested(&["i", "q"]).unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Quit));
+    let err = nested(&["end", "enter"]).unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Quit));
+    assert!(breadcrumbs().is_empty());
+}
+
+#[test]
+fn pickers_go_back_on_esc() {
+    input::script(&["x", "esc", "esc"]);
+    let finder = Finder {
+        prompt: "Find one.",
+        candidates: vec![candidate("main", 0)],
+    };
+    let err = finder.find().unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Back));
+
+    input::script(&["1", "esc", "esc"]);
+    let picker = Picker {
+        prompt: "Pick one.",
+        items: (1..=12).map(|n| n.to_string()).collect(),
+    };
+    let err = picker.pick().unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Back));
+}
+
+fn check(keys: &[&str]) -> anyhow::Result<Vec<String>> {
+    input::script(keys);
+    Checklist {
+        prompt: "Check some.",
+        items: [
+            ("a.txt", "modified"),
+            ("b.txt", "untracked"),
+            ("c.txt", "modified"),
+        ]
+        .into_iter()
+        .map(|(value, label)| Check {
+            value: value.to_string(),
+            label: label.to_string(),
+        })
+        .collect(),
+        filters: vec![
+            CheckFilter {
+                key: 'm',
+                label: "modified",
+            },
+            CheckFilter {
+                key: 'u',
+                label: "untracked",
+            },
+        ],
+    }
+    .select()
+}
+
+#[test]
+fn checklists_pick_any_number_of_items() {
+    assert_eq!(
+        check(&["space", "down", "down", "space", "enter"]).unwrap(),
+        ["a.txt", "c.txt"]
+    );
+    assert_eq!(check(&["a", "enter"]).unwrap(), ["a.txt", "b.txt", "c.txt"]);
+    assert_eq!(
+        check(&["a", "a", "j", "space", "enter"]).unwrap(),
+        ["b.txt"]
+    );
+    // without any checked item Enter picks the highlighted one
+    assert_eq!(check(&["end", "enter"]).unwrap(), ["c.txt"]);
+}
+
+#[test]
+fn checklist_filters_narrow_the_items() {
+    assert_eq!(check(&["u", "enter"]).unwrap(), ["b.txt"]);
+    assert_eq!(check(&["m", "a", "enter"]).unwrap(), ["a.txt", "c.txt"]);
+    // pressing a filter again shows everything
+    assert_eq!(check(&["u", "u", "end", "enter"]).unwrap(), ["c.txt"]);
+    let err = check(&["esc"]).unwrap_err();
+    assert_eq!(Navigation::of(&err), Some(Navigation::Back));
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{strip_colors, TestRepo};
+use gitflow::flow::{EXIT_GIT, EXIT_QUIT};
+use serde_json::{json, Value};
+
+/// Runs gitflow with JSON output and returns its events.
+fn events(repo: &TestRepo, args: &[&str]) -> Vec<Value> {
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["--output", "json"])
+        .args(args)
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn of_kind<'a>(events: &'a [Value], event: &str) -> Vec<&'a Value> {
+    events.iter().filter(|e| e["event"] == event).collect()
+}
+
+#[test]
+fn reports_a_flow_as_events() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let events = events(&repo, &["-a", "--keys", "a,y,q"]);
+
+    let statuses: Vec<_> = of_kind(&events, "status")
+        .iter()
+        .map(|e| e["status"].clone())
+        .collect();
+    assert_eq!(statuses, ["Unstaged", "FullyStaged"]);
+    let status = of_kind(&events, "status")[0];
+    assert_eq!(status["repository"]["upstream"], "origin/main");
+    assert_eq!(
+        status["repository"]["files"][0],
+        json!({
+            "path": "a.txt",
+            "orig_path": null,
+            "index": "Unmodified",
+            "worktree": "Untracked",
+            "conflicted": false,
+        })
+    );
+
+    let prompt = of_kind(&events, "prompt")[0];
+    assert_eq!(prompt["prompt"], "Files unstaged.");
+    assert_eq!(
+        prompt["options"][0],
+        json!({"key": "A", "description": "Add all files."})
+    );
+    assert_eq!(
+        of_kind(&events, "choice")[0],
+        &json!({
+            "event": "choice",
+            "prompt": "Files unstaged.",
+            "key": "A",
+            "description": "Add all files.",
+        })
+    );
+
+    let add = of_kind(&events, "command")
+        .into_iter()
+        .find(|e| e["command"] == "git add --all")
+        .unwrap();
+    assert_eq!(add["exit_code"], 0);
+    assert_eq!(add["kind"], "change");
+    assert_eq!(
+        events.last().unwrap(),
+        &json!({"event": "exit", "code": EXIT_QUIT})
+    );
+}
+
+#[test]
+fn reports_failed_commands() {
+    let repo = TestRepo::with_origin();
+    fs::remove_dir_all(repo.origin.as_ref().unwrap()).unwrap();
+    let events = events(&repo, &["-a", "--keys", "f"]);
+
+    let fetch = of_kind(&events, "command")
+        .into_iter()
+        .find(|e| e["command"] == "git fetch origin")
+        .unwrap();
+    assert_ne!(fetch["exit_code"], 0);
+    assert!(!fetch["stderr"].as_str().unwrap().is_empty());
+    let error = of_kind(&events, "error")[0];
+    assert_eq!(error["exit_code"], EXIT_GIT);
+    assert_eq!(
+        events.last().unwrap(),
+        &json!({"event": "exit", "code": EXIT_GIT})
+    );
+}
+
+#[test]
+fn every_line_is_an_event() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    for args in [&["status"][..], &["next"], &["-a", "--keys", "a,c"]] {
+        let events = events(&repo, args);
+        assert!(events.iter().all(|e| e["event"].is_string()));
+    }
+    let summary = &of_kind(&events(&repo, &["status"]), "summary")[0].clone();
+    assert_eq!(summary["status"], "Unstaged");
+    assert_eq!(summary["stashes"], 0);
+    let next = &of_kind(&events(&repo, &["next"]), "next")[0].clone();
+    assert_eq!(next["recommended"], "A");
+}
+
+/// Runs gitflow with styled text output and returns it without colors.
+fn text(repo: &TestRepo, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(args)
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    strip_colors(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[test]
+fn verbosity_decides_which_commands_are_shown() {
+    let repo = TestRepo::with_origin();
+    let run = |verbosity: &[&str]| {
+        repo.write("a.txt", "a\n");
+        repo.git(&["reset", "-q"]);
+        let mut args = verbosity.to_vec();
+        args.extend(["-a", "--keys", "a,y,q"]);
+        text(&repo, &args)
+    };
+    let add = "Executing: git add --all\n";
+    let query = "Executing: git status --porcelain=v2 --branch -z\n";
+    // the native backend reads the status in-process
+    let queries_run = !cfg!(feature = "native");
+
+    let quiet = run(&["-q"]);
+    assert!(!quiet.contains("Executing"));
+    assert!(quiet.contains("==> Files unstaged."));
+
+    let normal = run(&[]);
+    assert!(normal.contains(add));
+    assert!(!normal.contains(query));
+    assert!(!normal.contains("Took"));
+
+    let verbose = run(&["-v"]);
+    assert!(verbose.contains(add));
+    assert_eq!(verbose.contains(query), queries_run);
+    assert!(!verbose.contains("Took"));
+
+    let debug = run(&["-vv"]);
+    assert!(debug.starts_with("gitflow "));
+    assert!(debug.contains(&format!("{}Took ", add)));
+}
+
+#[test]
+fn invalid_keys_redraw_the_menu_in_place() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .args(["-a", "--keys", "z,z,q"])
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // what each redraw drew, up to where the next one moves the cursor back
+    let redraws: Vec<_> = stdout.split("\x1b[J").skip(1).collect();
+    assert_eq!(redraws.len(), 2);
+    let (drawn, moved) = redraws[0].rsplit_once("\x1b[").unwrap();
+    assert!(strip_colors(drawn).contains("Invalid option"));
+    assert_eq!(moved, format!("{}F", drawn.matches('\n').count()));
+}
@@ -0,0 +1,268 @@
+mod common;
+
+use common::TestRepo;
+use gitflow::status::{FileState, GitOperation, GitStatus, RepoSnapshot, SubmoduleState};
+
+macro_rules! assert_status {
+    ($repo:expr, $status:pat) => {
+        let status = $repo.status();
+        assert!(matches!(status, $status), "unexpected status {:?}", status);
+    };
+}
+
+#[test]
+fn parses_branch_headers() {
+    let snapshot = RepoSnapshot::parse(
+        "# branch.oid 1234\0# branch.head main\0# branch.upstream origin/main\0# branch.ab +2 -3\0",
+    )
+    .unwrap();
+    assert_eq!(snapshot.oid.as_deref(), Some("1234"));
+    assert_eq!(snapshot.branch.as_deref(), Some("main"));
+    assert_eq!(snapshot.upstream.as_deref(), Some("origin/main"));
+    assert_eq!((snapshot.ahead, snapshot.behind), (2, 3));
+
+    let snapshot =
+        RepoSnapshot::parse("# branch.oid (initial)\0# branch.head (detached)\0").unwrap();
+    assert_eq!(snapshot.oid, None);
+    assert_eq!(snapshot.branch, None);
+}
+
+#[test]
+fn dirty_tree_ahead_and_behind_is_partially_committed() {
+    let snapshot = RepoSnapshot::parse(
+        "# branch.oid 1234\0# branch.head main\0# branch.upstream origin/main\0# branch.ab +1 -2\0\
+         1 .M N... 100644 100644 100644 abcd abcd a.txt\0",
+    )
+    .unwrap();
+    assert!(matches!(
+        GitStatus::of(Some(&snapshot)),
+        GitStatus::PartiallyCommited
+    ));
+}
+
+#[test]
+fn parses_renames_with_spaces() {
+    let snapshot = RepoSnapshot::parse(
+        "2 R. N... 100644 100644 100644 abcd abcd R100 new name.txt\0old name.txt\0? un tracked\0",
+    )
+    .unwrap();
+    let rename = &snapshot.entries[0];
+    assert_eq!(rename.path, "new name.txt");
+    assert_eq!(rename.orig_path.as_deref(), Some("old name.txt"));
+    assert_eq!(rename.index, FileState::Renamed);
+    assert!(rename.is_staged() && !rename.is_unstaged());
+
+    let untracked = &snapshot.entries[1];
+    assert_eq!(untracked.path, "un tracked");
+    assert_eq!(untracked.worktree, FileState::Untracked);
+}
+
+#[test]
+fn parses_every_conflict_code() {
+    for xy in ["DD", "AU", "UD", "UA", "DU", "AA", "UU"] {
+        let snapshot = RepoSnapshot::parse(&format!(
+            "# branch.oid 1234\0u {} N... 100644 100644 100644 100644 a b c file\0",
+            xy
+        ))
+        .unwrap();
+        assert!(snapshot.has_conflicts(), "{} is a conflict", xy);
+        assert!(matches!(
+            GitStatus::of(Some(&snapshot)),
+            GitStatus::Conflicted
+        ));
+    }
+}
+
+#[test]
+fn parses_submodule_flags() {
+    let snapshot =
+        RepoSnapshot::parse("1 .M S.MU 160000 160000 160000 abcd abcd vendor/lib\0").unwrap();
+    assert_eq!(
+        snapshot.entries[0].submodule,
+        Some(SubmoduleState {
+            commit_changed: false,
+            modified: true,
+            untracked: true,
+        })
+    );
+}
+
+#[test]
+fn rejects_malformed_records() {
+    assert!(RepoSnapshot::parse("1 M\0").is_err());
+    assert!(RepoSnapshot::parse("1 XY N... 1 1 1 a b path\0").is_err());
+    assert!(RepoSnapshot::parse("2 R. N... 100644 100644 100644 a b R100 only-new\0").is_err());
+}
+
+#[test]
+fn uninitialized() {
+    let repo = TestRepo::uninitialized();
+    assert_status!(repo, GitStatus::Uninitialized);
+}
+
+#[test]
+fn initialized() {
+    let repo = TestRepo::initialized();
+    repo.write("a.txt", "a");
+    assert_status!(repo, GitStatus::Initialized);
+}
+
+#[test]
+fn clean() {
+    let repo = TestRepo::local();
+    assert_status!(repo, GitStatus::Clean);
+}
+
+#[test]
+fn unstaged() {
+    let repo = TestRepo::local();
+    repo.write("README.md", "changed\n");
+    assert_status!(repo, GitStatus::Unstaged);
+}
+
+#[test]
+fn partially_staged() {
+    let repo = TestRepo::local();
+    repo.write("README.md", "changed\n");
+    repo.write("new.txt", "new\n");
+    repo.git(&["add", "new.txt"]);
+    assert_status!(repo, GitStatus::PartiallyStaged);
+}
+
+#[test]
+fn fully_staged() {
+    let repo = TestRepo::local();
+    repo.write("README.md", "changed\n");
+    repo.git(&["add", "README.md"]);
+    assert_status!(repo, GitStatus::FullyStaged);
+}
+
+#[test]
+fn up_to_date() {
+    let repo = TestRepo::with_origin();
+    assert_status!(repo, GitStatus::UpToDate);
+}
+
+#[test]
+fn fully_committed() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    assert_status!(repo, GitStatus::FullyCommited);
+}
+
+#[test]
+fn partially_committed() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.write("README.md", "changed\n");
+    assert_status!(repo, GitStatus::PartiallyCommited);
+}
+
+#[test]
+fn mess_fully_committed() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.write("README.md", "changed\n");
+    repo.git(&["add", "README.md"]);
+    assert_status!(repo, GitStatus::MessFullyCommited);
+}
+
+#[test]
+fn mess_partially_committed() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.write("README.md", "changed\n");
+    repo.git(&["add", "README.md"]);
+    repo.write("a.txt", "changed\n");
+    assert_status!(repo, GitStatus::MessPartiallyCommited);
+}
+
+#[test]
+fn behind() {
+    let repo = TestRepo::with_origin();
+    repo.push_from_elsewhere("remote.txt", "remote\n");
+    assert_status!(repo, GitStatus::Behind);
+}
+
+#[test]
+fn diverged() {
+    let repo = TestRepo::with_origin();
+    repo.push_from_elsewhere("remote.txt", "remote\n");
+    repo.commit_file("local.txt", "local\n");
+    let snapshot = repo.snapshot().unwrap();
+    assert_eq!((snapshot.ahead, snapshot.behind), (1, 1));
+    assert_status!(repo, GitStatus::Diverged);
+}
+
+#[test]
+fn conflicted() {
+    let repo = TestRepo::local();
+    repo.write("README.md", "stashed\n");
+    repo.git(&["stash", "-q"]);
+    repo.commit_file("README.md", "committed\n");
+    repo.git_fails(&["stash", "pop"]);
+    assert_status!(repo, GitStatus::Conflicted);
+}
+
+/// Leaves `feature` and `main` with conflicting edits of README.md, `main` checked out.
+fn conflicting_branches(repo: &TestRepo) {
+    repo.git(&["checkout", "-q", "-b", "feature"]);
+    repo.commit_file("README.md", "feature\n");
+    repo.git(&["checkout", "-q", "main"]);
+    repo.commit_file("README.md", "main\n");
+}
+
+#[test]
+fn merging() {
+    let repo = TestRepo::local();
+    conflicting_branches(&repo);
+    repo.git_fails(&["merge", "feature"]);
+    assert_status!(repo, GitStatus::Merging);
+    assert_eq!(
+        repo.snapshot().unwrap().operation,
+        Some(GitOperation::Merge)
+    );
+}
+
+#[test]
+fn rebasing() {
+    let repo = TestRepo::local();
+    conflicting_branches(&repo);
+    repo.git_fails(&["rebase", "feature"]);
+    assert_status!(repo, GitStatus::Rebasing);
+}
+
+#[test]
+fn cherry_picking() {
+    let repo = TestRepo::local();
+    conflicting_branches(&repo);
+    repo.git_fails(&["cherry-pick", "feature"]);
+    assert_status!(repo, GitStatus::CherryPicking);
+}
+
+#[test]
+fn reverting() {
+    let repo = TestRepo::local();
+    repo.commit_file("README.md", "first\n");
+    repo.commit_file("README.md", "second\n");
+    repo.git_fails(&["revert", "--no-edit", "HEAD~1"]);
+    assert_status!(repo, GitStatus::Reverting);
+}
+
+#[test]
+fn bisecting() {
+    let repo = TestRepo::local();
+    repo.commit_file("a.txt", "a\n");
+    repo.git(&["bisect", "start", "HEAD", "HEAD~1"]);
+    assert_status!(repo, GitStatus::Bisecting);
+}
+
+#[test]
+fn applying() {
+    let repo = TestRepo::local();
+    conflicting_branches(&repo);
+    let patch = repo.git(&["format-patch", "-1", "feature", "--stdout"]);
+    repo.write("feature.patch", &format!("{}\n", patch));
+    repo.git_fails(&["am", "feature.patch"]);
+    assert_status!(repo, GitStatus::Applying);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::process::Command;
+
+use common::{strip_colors, TestRepo};
+use gitflow::{
+    backend::{MemoryBackend, MemoryRepo},
+    status::{FileState, GitOperation},
+    summary::Summary,
+};
+
+fn gitflow_status(repo: &TestRepo) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_gitflow"))
+        .arg("status")
+        .current_dir(&repo.dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    strip_colors(&String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn groups_files_by_state() {
+    let mut repo = MemoryRepo::new("main")
+        .with_upstream("origin", "main", 2, 1)
+        .with_file("src/lib.rs", FileState::Modified, FileState::Modified)
+        .with_file("notes.md", FileState::Untracked, FileState::Untracked)
+        .with_conflict("Cargo.lock");
+    repo.entries[1].index = FileState::Unmodified;
+    repo.operation = Some(GitOperation::Rebase);
+    repo.stashes = 2;
+    let summary = Summary::collect(&MemoryBackend::new(repo)).unwrap();
+    assert_eq!(
+        summary.to_string(),
+        "On branch main\n\
+         Upstream: origin/main, 2 ahead, 1 behind\n\
+         Status: Rebasing\n\
+         In progress: rebase\n\
+         Stashes: 2\n\
+         \n\
+         Staged (1):\n\
+         \x20 modified:   src/lib.rs\n\
+         \n\
+         Unstaged (1):\n\
+         \x20 modified:   src/lib.rs\n\
+         \n\
+         Untracked (1):\n\
+         \x20 notes.md\n\
+         \n\
+         Conflicted (1):\n\
+         \x20 Cargo.lock\n\
+         \n\
+         Changes against origin/main:\n\
+         \x20 src/lib.rs | changed\n\
+         \x20 notes.md | changed\n\
+         \x20 Cargo.lock | changed\n"
+    );
+}
+
+#[test]
+fn summarizes_a_repository() {
+    let repo = TestRepo::with_origin();
+    repo.commit_file("a.txt", "a\n");
+    repo.git(&["mv", "a.txt", "b.txt"]);
+    repo.write("c.txt", "c\n");
+    repo.git(&["stash", "push", "--include-untracked", "--", "c.txt"]);
+    repo.write("d.txt", "d\n");
+
+    let branch = repo.git(&["branch", "--show-current"]);
+    assert_eq!(
+        gitflow_status(&repo),
+        format!(
+            "On branch {branch}\n\
+             Upstream: origin/{branch}, 1 ahead, 0 behind\n\
+             Status: MessPartiallyCommited\n\
+             Stashes: 1\n\
+             \n\
+             Staged (1):\n\
+             \x20 renamed:    a.txt -> b.txt\n\
+             \n\
+             Untracked (1):\n\
+             \x20 d.txt\n\
+             \n\
+             Changes against origin/{branch}:\n\
+             \x20 b.txt | 1 +\n\
+             \x20 1 file changed, 1 insertion(+)\n"
+        )
+    );
+}
+
+#[test]
+fn outside_a_repository() {
+    let repo = TestRepo::uninitialized();
+    assert_eq!(gitflow_status(&repo), "Not a git repository.\n");
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::process::Command;
+
+use common::TestRepo;
+use crossterm::style::Color;
+use gitflow::{config::Config, theme::Theme};
+
+fn theme(toml: &str) -> anyhow::Result<Theme> {
+    let config: Config = toml::from_str(toml).unwrap();
+    Theme::from_config(&config)
+}
+
+#[test]
+fn selects_built_in_themes() {
+    assert_eq!(theme("").unwrap(), Theme::DARK);
+    assert_eq!(theme("theme = \"light\"").unwrap().background, None);
+    for name in Theme::NAMES {
+        assert_eq!(
+            theme(&format!("theme = \"{}\"", name)).unwrap(),
+            Theme::named(name).unwrap()
+        );
+    }
+    let err = theme("theme = \"solarized\"").unwrap_err();
+    assert!(err.to_string().starts_with("Unknown theme: solarized"));
+}
+
+#[test]
+fn palettes_override_their_base() {
+    let mine = theme(
+        "theme = \"mine\"\n\
+         [themes.mine]\n\
+         base = \"light\"\n\
+         error = \"#ff8800\"\n\
+         code-background = \"none\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        mine,
+        Theme {
+            error: Some(Color::Rgb {
+                r: 0xff,
+                g: 0x88,
+                b: 0
+            }),
+            code_background: None,
+            ..Theme::LIGHT
+        }
+    );
+
+    for (palette, message) in [
+        ("error = \"red\"", "Invalid error of theme mine"),
+        (
+            "errors = \"#ff0000\"",
+            "Unknown color of theme mine: errors",
+        ),
+        ("base = \"mine\"", "Unknown base of theme mine: mine"),
+    ] {
+        let err = theme(&format!("theme = \"mine\"\n[themes.mine]\n{}\n", palette)).unwrap_err();
+        assert!(err.to_string().starts_with(message), "{:#}", err);
+    }
+}
+
+#[test]
+fn plain_text_when_not_a_terminal() {
+    let repo = TestRepo::with_origin();
+    repo.write("a.txt", "a\n");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_gitflow"))
+            .args(args)
+            .current_dir(&repo.dir)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--theme", "high-contrast", "status"]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("On branch "));
+    assert!(!text.contains('\x1b'));
+
+    let output = run(&["--theme", "solarized", "status"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown theme: solarized"));
+}